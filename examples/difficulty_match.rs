@@ -0,0 +1,80 @@
+//! plays the difficulty levels against their neighbours to calibrate them
+//!
+//! the games of all pairings are rated together with Bradley-Terry ratings, so a level
+//! that wins every game still gets a finite rating. Without the `tensorflow` feature or
+//! with `heuristic` as model the agents use the `HeuristicEvaluator`.
+//!
+//! usage: `cargo run --release --example difficulty_match -- [games per pairing] [model path | heuristic]`
+use std::sync::Arc;
+use rustai_abalone::arena::{Arena, ArenaOptions};
+use rustai_abalone::config::{AgentConfig, Difficulty};
+use rustai_abalone::evaluator::{Evaluator, HeuristicEvaluator};
+use rustai_abalone::game::BELGIAN_DAISY;
+use rustai_abalone::player::MagisterLudi;
+use rustai_abalone::ratings::{GameResult, RatingOptions, RatingTable};
+
+// creates a seeded configuration for the level
+fn level_config(level: Difficulty, seed: u64) -> AgentConfig {
+    AgentConfig::builder()
+        .difficulty(level)
+        .mcts_parallel(4)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+// plays every pair of neighbouring levels and prints the results and the rating of every level
+fn calibrate<M, F>(num_games: usize, create_agent: F)
+where
+    M: Evaluator + ?Sized + 'static,
+    F: Fn(Difficulty, u64) -> MagisterLudi<M>,
+{
+    let mut results = Vec::new();
+    for pair in Difficulty::ALL.windows(2) {
+        let (weaker, stronger) = (pair[0], pair[1]);
+        let options = ArenaOptions {
+            games: num_games,
+            ..Default::default()
+        };
+        let mut arena = Arena::new(create_agent(stronger, 2), create_agent(weaker, 1), options);
+        let result = arena.run(|game, _| {
+            let (white, black) = if game.candidate_white { (stronger, weaker) } else { (weaker, stronger) };
+            results.push(GameResult::new(&white.to_string(), &black.to_string(), game.result));
+        });
+        let (lower, upper) = result.confidence_interval();
+        println!(
+            "{stronger} vs {weaker}: +{} ={} -{}, score {:.1}% (95% CI {:.1}% to {:.1}%), {:+.0} Elo",
+            result.wins,
+            result.draws,
+            result.losses,
+            100.0 * result.score(),
+            100.0 * lower,
+            100.0 * upper,
+            result.elo()
+        );
+        let (mut strong_agent, mut weak_agent) = arena.into_agents();
+        weak_agent.stop_execution();
+        strong_agent.stop_execution();
+    }
+    print!("{}", RatingTable::from_results(&results, RatingOptions::default()));
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let num_games: usize = args.get(1).map_or(10, |num| num.parse().expect("number of games expected"));
+    let model = args.get(2).map(|path| path.as_str());
+
+    match model {
+        #[cfg(feature = "tensorflow")]
+        Some(model_path) if model_path != "heuristic" => calibrate(num_games, |level, seed| {
+            let mut config = level_config(level, seed);
+            config.model_path = Some(model_path.to_string());
+            MagisterLudi::with_config(BELGIAN_DAISY, config)
+        }),
+        #[cfg(feature = "tensorflow")]
+        None => calibrate(num_games, |level, seed| MagisterLudi::with_config(BELGIAN_DAISY, level_config(level, seed))),
+        _ => calibrate(num_games, |level, seed| {
+            MagisterLudi::with_evaluator(BELGIAN_DAISY, level_config(level, seed), Arc::new(HeuristicEvaluator::default()))
+        }),
+    }
+}
@@ -0,0 +1,25 @@
+//! plays a game against the agent or a simple bot on the terminal
+//!
+//! usage: `cargo run --release --example human_match -- [agent|greedy|random] [black|white] [difficulty]`
+use rustai_abalone::config::Difficulty;
+use rustai_abalone::game::BELGIAN_DAISY;
+use rustai_abalone::player::MagisterLudi;
+use rustai_abalone::players::{GreedyPlayer, HumanPlayer, Match, Player, RandomPlayer};
+use rustai_abalone::selfplay::DEFAULT_MAX_MOVES;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let opponent: Box<dyn Player> = match args.get(1).map_or("agent", |name| name.as_str()) {
+        "random" => Box::new(RandomPlayer::new(None)),
+        "greedy" => Box::new(GreedyPlayer::heuristic(None)),
+        _ => {
+            let difficulty: Difficulty = args.get(3).map_or(Difficulty::Intermediate, |level| level.parse().expect("unknown difficulty"));
+            Box::new(MagisterLudi::with_config(BELGIAN_DAISY, difficulty.config()))
+        }
+    };
+    let human: Box<dyn Player> = Box::new(HumanPlayer::stdin("human"));
+    let human_black = args.get(2).map(|colour| colour.as_str()) != Some("white");
+    let (white, black) = if human_black { (opponent, human) } else { (human, opponent) };
+    let result = Match::new(white, black).play(BELGIAN_DAISY, DEFAULT_MAX_MOVES);
+    println!("{} after {} moves: {:?}", result.game_result(), result.num_moves, result.termination);
+}
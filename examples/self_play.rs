@@ -0,0 +1,97 @@
+//! lets the agent play against itself on several workers and writes the positions as NPZ training shards
+//!
+//! usage: `cargo run --release --example self_play -- [output dir] [games] [games per shard] [workers] [model path]`
+//!
+//! every shard can be loaded in Python with `numpy.load` and contains the arrays `boards`, `histories`,
+//! `history_lens`, `black_tomove`, `move_numbers`, `policies` and `values`, see `rustai_abalone::selfplay::write_shard`.
+//! The games share a pool of search threads, one per CPU. The first Ctrl-C lets the running games
+//! finish without starting new ones, the second gives them up. The finished games are still written.
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rustai_abalone::batch::{BatchedModel, DEFAULT_MAX_BATCH, DEFAULT_MAX_WAIT};
+use rustai_abalone::config::{AgentConfig, ModelSignature};
+use rustai_abalone::player::MoveSelection;
+use rustai_abalone::registry::ModelRegistry;
+use rustai_abalone::scheduler::{SchedulerOptions, SelfPlayScheduler};
+use rustai_abalone::selfplay::write_shard;
+use rustai_abalone::store::ModelStore;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let out_dir = PathBuf::from(args.get(1).map_or("self_play", |dir| dir.as_str()));
+    let num_games: usize = args.get(2).map_or(10, |num| num.parse().expect("number of games expected"));
+    let games_per_shard: usize = args.get(3).map_or(5, |num| num.parse().expect("number of games per shard expected"));
+    let workers: usize = args.get(4).map_or(4, |num| num.parse().expect("number of workers expected"));
+    std::fs::create_dir_all(&out_dir).expect("could not create the output directory");
+
+    let model = match args.get(5) {
+        Some(path) => ModelRegistry::global().load(path, &ModelSignature::default()),
+        None => {
+            let installed = ModelStore::default().default_model().expect("no model available");
+            ModelRegistry::global().load(&installed.model_path(), &installed.manifest.signature)
+        }
+    };
+    let options = SchedulerOptions {
+        workers,
+        games: Some(num_games),
+        ..Default::default()
+    };
+    // the positions of all workers and search threads are predicted together
+    let max_batch = DEFAULT_MAX_BATCH.min(workers + options.search_threads);
+    let evaluator = Arc::new(BatchedModel::new(model, max_batch, DEFAULT_MAX_WAIT));
+    let config = AgentConfig::builder()
+        .mcts_num(200)
+        .mcts_depth(10)
+        .move_selection(MoveSelection::self_play())
+        .build()
+        .unwrap();
+    let scheduler = SelfPlayScheduler::new(config, evaluator.clone(), options);
+    let stop_handle = scheduler.stop_handle();
+    ctrlc::set_handler(move || {
+        if stop_handle.is_draining() {
+            println!("stopping after the current moves");
+            stop_handle.stop();
+        } else {
+            println!("finishing the running games, press Ctrl-C again to give them up");
+            stop_handle.drain();
+        }
+    })
+    .expect("could not set the Ctrl-C handler");
+
+    let mut records = Vec::new();
+    let mut shard = 0;
+    let mut write_records = |records: &mut Vec<_>| {
+        let path = out_dir.join(format!("shard_{shard:05}.npz"));
+        write_shard(&path, records).expect("could not write the shard");
+        println!("wrote {} positions to {}", records.len(), path.display());
+        records.clear();
+        shard += 1;
+    };
+    let progress = scheduler.run(|game_id, game, progress| {
+        println!(
+            "game {game_id}: result {} after {} moves, {}/{num_games} games, {:.1} games per hour",
+            game.result,
+            game.num_moves,
+            progress.games,
+            progress.games_per_hour()
+        );
+        records.extend(game.records);
+        if progress.games % games_per_shard == 0 {
+            write_records(&mut records);
+        }
+    });
+    if !records.is_empty() {
+        write_records(&mut records);
+    }
+    println!(
+        "{} games ({} white wins, {} black wins, {} draws) with {} positions in {:.0?}, average batch size {:.1}",
+        progress.games,
+        progress.white_wins,
+        progress.black_wins,
+        progress.draws,
+        progress.positions,
+        progress.elapsed,
+        evaluator.stats().average_batch_size()
+    );
+}
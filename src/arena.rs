@@ -0,0 +1,398 @@
+use super::evaluator::Evaluator;
+use super::game::{AbaloneGame, Board, BELGIAN_DAISY, CLASSIC, GERMAN_DAISY};
+use super::player::{AgentAction, DefaultEvaluator, MagisterLudi};
+use super::selfplay::DEFAULT_MAX_MOVES;
+
+/// quantile of the standard normal distribution for a two-sided 95% confidence interval
+const Z_95: f64 = 1.959964;
+
+/// returns the Elo difference that corresponds to an expected score
+///
+/// # Arguments
+///
+/// * `score` - expected score between 0 and 1, a draw counts as half a win
+///
+/// # Returns
+///
+/// * `elo` - Elo difference, infinite for a score of 0 or 1
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::arena::{elo_from_score, score_from_elo};
+/// assert_eq!(elo_from_score(0.5), 0.0);
+/// assert!((elo_from_score(score_from_elo(100.0)) - 100.0).abs() < 1e-9);
+/// ```
+pub fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// returns the expected score of a player that is rated higher by the given Elo difference
+///
+/// # Arguments
+///
+/// * `elo` - Elo difference
+pub fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// parameters of a sequential probability ratio test whether the candidate is stronger
+///
+/// the test compares the hypothesis that the candidate is `elo0` stronger than the baseline
+/// with the hypothesis that it is `elo1` stronger
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sprt {
+    /// Elo difference of the null hypothesis
+    pub elo0: f64,
+    /// Elo difference of the alternative hypothesis
+    pub elo1: f64,
+    /// probability to accept the alternative hypothesis although the null hypothesis is true
+    pub alpha: f64,
+    /// probability to accept the null hypothesis although the alternative hypothesis is true
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    /// returns the bounds of the log-likelihood ratio, the test ends as soon as one is crossed
+    ///
+    /// # Returns
+    ///
+    /// * `(lower, upper)` - below lower the null hypothesis and above upper the alternative hypothesis is accepted
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+}
+
+/// decision of a `Sprt`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SprtDecision {
+    /// the candidate is stronger by at least `elo1`, it can be promoted
+    AcceptH1,
+    /// the candidate is not stronger than `elo0`, it should be rejected
+    AcceptH0,
+    /// more games are needed
+    Continue,
+}
+
+/// outcome of a `Sprt` for the games played so far
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SprtResult {
+    /// log-likelihood ratio of the alternative and the null hypothesis
+    pub llr: f64,
+    /// bound below which the null hypothesis is accepted
+    pub lower: f64,
+    /// bound above which the alternative hypothesis is accepted
+    pub upper: f64,
+    /// decision of the test
+    pub decision: SprtDecision,
+}
+
+/// results of the candidate in an arena
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ArenaResult {
+    /// number of games the candidate won
+    pub wins: usize,
+    /// number of drawn or adjudicated games
+    pub draws: usize,
+    /// number of games the candidate lost
+    pub losses: usize,
+}
+
+impl ArenaResult {
+    /// returns the number of played games
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// returns the average score of the candidate, a draw counts as half a win
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::arena::ArenaResult;
+    /// let result = ArenaResult { wins: 6, draws: 2, losses: 2 };
+    /// assert_eq!(result.score(), 0.7);
+    /// ```
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+
+    // variance of the score of a single game around the given mean, the counts are shifted by pseudo games
+    fn score_variance(&self, mean: f64, pseudo_games: f64) -> f64 {
+        let wins = self.wins as f64 + pseudo_games;
+        let draws = self.draws as f64 + pseudo_games;
+        let losses = self.losses as f64 + pseudo_games;
+        (wins * (1.0 - mean).powi(2) + draws * (0.5 - mean).powi(2) + losses * mean.powi(2)) / (wins + draws + losses)
+    }
+
+    /// returns the 95% confidence interval of the score by the normal approximation
+    ///
+    /// # Returns
+    ///
+    /// * `(lower, upper)` - bounds of the interval between 0 and 1
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::arena::ArenaResult;
+    /// let (lower, upper) = ArenaResult { wins: 60, draws: 0, losses: 40 }.confidence_interval();
+    /// assert!(lower < 0.6 && 0.6 < upper);
+    /// ```
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        if self.games() == 0 {
+            return (0.0, 1.0);
+        }
+        let score = self.score();
+        let margin = Z_95 * (self.score_variance(score, 0.0) / self.games() as f64).sqrt();
+        ((score - margin).max(0.0), (score + margin).min(1.0))
+    }
+
+    /// returns the estimated Elo difference of the candidate to the baseline
+    pub fn elo(&self) -> f64 {
+        elo_from_score(self.score())
+    }
+
+    /// returns the 95% confidence interval of the Elo difference
+    pub fn elo_interval(&self) -> (f64, f64) {
+        let (lower, upper) = self.confidence_interval();
+        (elo_from_score(lower), elo_from_score(upper))
+    }
+
+    /// evaluates the sequential probability ratio test for the games played so far
+    ///
+    /// the log-likelihood ratio is approximated with the normal distribution of the score.
+    /// Its variance is estimated with half a pseudo game added to every outcome, so that a
+    /// streak at the start does not end the test at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `sprt` - hypotheses and error probabilities
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::arena::{ArenaResult, Sprt, SprtDecision};
+    /// let result = ArenaResult { wins: 300, draws: 100, losses: 100 };
+    /// assert_eq!(result.sprt(&Sprt::default()).decision, SprtDecision::AcceptH1);
+    /// ```
+    pub fn sprt(&self, sprt: &Sprt) -> SprtResult {
+        let (lower, upper) = sprt.bounds();
+        let (score0, score1) = (score_from_elo(sprt.elo0), score_from_elo(sprt.elo1));
+        let score = self.score();
+        let variance = self.score_variance(score, 0.5);
+        let llr = (score1 - score0) * (2.0 * score - score0 - score1) * self.games() as f64 / (2.0 * variance);
+        let decision = if llr >= upper {
+            SprtDecision::AcceptH1
+        } else if llr <= lower {
+            SprtDecision::AcceptH0
+        } else {
+            SprtDecision::Continue
+        };
+        SprtResult {
+            llr,
+            lower,
+            upper,
+            decision,
+        }
+    }
+}
+
+/// options of an `Arena`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArenaOptions {
+    /// maximum number of games
+    pub games: usize,
+    /// starting positions, every one is played twice in a row with swapped colours
+    pub layouts: Vec<Board>,
+    /// games are adjudicated as draw after this many moves
+    pub max_moves: usize,
+    /// if given, the arena stops as soon as the test reaches a decision
+    pub sprt: Option<Sprt>,
+}
+
+impl Default for ArenaOptions {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            layouts: vec![BELGIAN_DAISY, GERMAN_DAISY, CLASSIC],
+            max_moves: DEFAULT_MAX_MOVES,
+            sprt: None,
+        }
+    }
+}
+
+/// single game of an arena
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ArenaGame {
+    /// index of the game, starting at 0
+    pub index: usize,
+    /// index of the starting position in `ArenaOptions::layouts`
+    pub layout: usize,
+    /// true if the candidate played white
+    pub candidate_white: bool,
+    /// result of the game, 1 if white won, -1 if black won and 0 for a draw or an adjudicated game
+    pub result: i8,
+    /// number of moves played
+    pub num_moves: usize,
+}
+
+impl ArenaGame {
+    /// returns the result of the game for the candidate, 1 for a win, -1 for a loss and 0 for a draw
+    pub fn candidate_result(&self) -> i8 {
+        if self.candidate_white {
+            self.result
+        } else {
+            -self.result
+        }
+    }
+}
+
+/// plays games between a candidate and a baseline agent to decide whether the candidate is stronger
+///
+/// the agents may use different models or search settings. The colours alternate every game
+/// and the starting layout every second game, so each layout is played with both colours.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use rustai_abalone::arena::{Arena, ArenaOptions};
+/// use rustai_abalone::config::AgentConfig;
+/// use rustai_abalone::evaluator::HeuristicEvaluator;
+/// use rustai_abalone::game::BELGIAN_DAISY;
+/// use rustai_abalone::player::MagisterLudi;
+/// let create_agent = |mcts_num| {
+///     let config = AgentConfig::builder().mcts_num(mcts_num).mcts_parallel(2).mcts_depth(3).build().unwrap();
+///     MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()))
+/// };
+/// let options = ArenaOptions { games: 2, max_moves: 4, ..Default::default() };
+/// let mut arena = Arena::new(create_agent(20), create_agent(10), options);
+/// let result = arena.run(|game, result| println!("game {}: {} after {} games", game.index, result.score(), result.games()));
+/// assert_eq!(result.games(), 2);
+/// let (mut candidate, mut baseline) = arena.into_agents();
+/// candidate.stop_execution();
+/// baseline.stop_execution();
+/// ```
+pub struct Arena<C: Evaluator + ?Sized + 'static = DefaultEvaluator, B: Evaluator + ?Sized + 'static = DefaultEvaluator> {
+    candidate: MagisterLudi<C>,
+    baseline: MagisterLudi<B>,
+    options: ArenaOptions,
+}
+
+impl<C: Evaluator + ?Sized + 'static, B: Evaluator + ?Sized + 'static> Arena<C, B> {
+    /// creates an arena for the two agents
+    ///
+    /// # Arguments
+    ///
+    /// * `candidate` - agent that is tested
+    /// * `baseline` - agent the candidate is compared with
+    /// * `options` - number of games, layouts and the optional SPRT
+    ///
+    /// # Panics
+    ///
+    /// will panic if no layout is given
+    pub fn new(candidate: MagisterLudi<C>, baseline: MagisterLudi<B>, options: ArenaOptions) -> Self {
+        assert!(!options.layouts.is_empty(), "the arena needs at least one layout");
+        Self {
+            candidate,
+            baseline,
+            options,
+        }
+    }
+
+    /// returns the agents and consumes the arena
+    pub fn into_agents(self) -> (MagisterLudi<C>, MagisterLudi<B>) {
+        (self.candidate, self.baseline)
+    }
+
+    /// plays the games and returns the results of the candidate
+    ///
+    /// # Arguments
+    ///
+    /// * `on_game` - called with the game and the results so far after every game
+    ///
+    /// # Panics
+    ///
+    /// will panic if one or more of the MCTS daemon threads of the agents are not active anymore
+    pub fn run<F: FnMut(&ArenaGame, &ArenaResult)>(&mut self, mut on_game: F) -> ArenaResult {
+        let mut result = ArenaResult::default();
+        for index in 0..self.options.games {
+            let layout = (index / 2) % self.options.layouts.len();
+            let candidate_white = index % 2 == 1;
+            let board = self.options.layouts[layout];
+            let (game_result, num_moves) = if candidate_white {
+                play_game(&mut self.candidate, &mut self.baseline, board, self.options.max_moves)
+            } else {
+                play_game(&mut self.baseline, &mut self.candidate, board, self.options.max_moves)
+            };
+            let game = ArenaGame {
+                index,
+                layout,
+                candidate_white,
+                result: game_result,
+                num_moves,
+            };
+            match game.candidate_result() {
+                1 => result.wins += 1,
+                -1 => result.losses += 1,
+                _ => result.draws += 1,
+            }
+            on_game(&game, &result);
+            let decided = self.options.sprt.is_some_and(|sprt| result.sprt(&sprt).decision != SprtDecision::Continue);
+            if decided {
+                break;
+            }
+        }
+        result
+    }
+}
+
+// plays a single game and returns the result from white's point of view and the number of moves
+fn play_game<W: Evaluator + ?Sized + 'static, K: Evaluator + ?Sized + 'static>(
+    white: &mut MagisterLudi<W>,
+    black: &mut MagisterLudi<K>,
+    board: Board,
+    max_moves: usize,
+) -> (i8, usize) {
+    white.start_new_game(board);
+    black.start_new_game(board);
+    let mut referee = AbaloneGame::new(board);
+    while !referee.get_game_ended() && referee.get_num_moves() < max_moves {
+        let black_tomove = referee.get_black_tomove();
+        let action = if black_tomove { black.own_move(true) } else { white.own_move(true) };
+        let offers_draw = matches!(action, AgentAction::MoveOfferingDraw(_));
+        match action.board() {
+            Some(next_state) => {
+                referee.update_state(next_state);
+                // the waiting agent follows the move and may accept an offered draw
+                let accepted = if black_tomove {
+                    white.external_move(next_state, true);
+                    offers_draw && white.consider_draw_offer()
+                } else {
+                    black.external_move(next_state, true);
+                    offers_draw && black.consider_draw_offer()
+                };
+                if accepted {
+                    referee.end_with_result(0);
+                }
+            }
+            None => referee.end_with_result(if black_tomove { 1 } else { -1 }),
+        }
+    }
+    let result = if referee.get_game_ended() { referee.get_game_result() } else { 0 };
+    (result, referee.get_num_moves())
+}
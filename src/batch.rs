@@ -0,0 +1,177 @@
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::evaluator::Position;
+use super::model::{PolicyValueModel, Prediction};
+
+/// default maximum number of positions the model predicts at once
+pub const DEFAULT_MAX_BATCH: usize = 64;
+
+/// default time the first position of a batch waits for further positions
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_millis(2);
+
+// channel returning a prediction with the index of its position to the caller
+type Reply = Sender<(usize, Prediction)>;
+
+// position waiting for its prediction, index is the position within the request of the caller
+struct BatchRequest {
+    position: Position,
+    index: usize,
+    reply: Reply,
+}
+
+/// number of predictions made by a `BatchedModel`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct BatchStats {
+    /// number of calls of the wrapped model
+    pub batches: usize,
+    /// number of predicted positions
+    pub positions: usize,
+}
+
+impl BatchStats {
+    /// returns the average number of positions per call of the wrapped model
+    pub fn average_batch_size(&self) -> f64 {
+        if self.batches == 0 {
+            return 0.0;
+        }
+        self.positions as f64 / self.batches as f64
+    }
+}
+
+/// model that collects the positions of concurrent callers and predicts them in batches
+///
+/// the MCTS evaluates one position at a time, which leaves a neural network mostly idle.
+/// When several searches share a `BatchedModel`, e.g. the workers of `scheduler::SelfPlayScheduler`,
+/// their positions are combined and the wrapped model is called by a single dispatcher thread.
+/// The dispatcher stops when the `BatchedModel` is dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use rustai_abalone::batch::{BatchedModel, DEFAULT_MAX_BATCH, DEFAULT_MAX_WAIT};
+/// use rustai_abalone::evaluator::Position;
+/// use rustai_abalone::game::BELGIAN_DAISY;
+/// use rustai_abalone::model::{PolicyValueModel, Prediction, NUM_MOVES};
+/// struct UniformModel;
+/// impl PolicyValueModel for UniformModel {
+///     fn predict(&self, positions: &[Position]) -> Vec<Prediction> {
+///         positions.iter().map(|_| Prediction { policy_logits: vec![0.0; NUM_MOVES], value: 0.0 }).collect()
+///     }
+/// }
+/// let model = BatchedModel::new(Arc::new(UniformModel), DEFAULT_MAX_BATCH, DEFAULT_MAX_WAIT);
+/// let predictions = model.predict(&[Position::from_board(BELGIAN_DAISY)]);
+/// assert_eq!(predictions.len(), 1);
+/// assert_eq!(model.stats().positions, 1);
+/// ```
+pub struct BatchedModel {
+    requests: Sender<BatchRequest>,
+    history_len: usize,
+    batches: Arc<AtomicUsize>,
+    positions: Arc<AtomicUsize>,
+}
+
+impl BatchedModel {
+    /// wraps the model and starts the dispatcher thread
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - model that predicts the batches
+    /// * `max_batch` - maximum number of positions per call of the model, see `DEFAULT_MAX_BATCH`
+    /// * `max_wait` - time the first position of a batch waits for further positions, see `DEFAULT_MAX_WAIT`
+    ///
+    /// # Panics
+    ///
+    /// will panic if `max_batch` is 0
+    pub fn new<M: PolicyValueModel + ?Sized + 'static>(model: Arc<M>, max_batch: usize, max_wait: Duration) -> Self {
+        assert!(max_batch > 0, "a batch needs at least one position");
+        let (requests, request_receiver) = unbounded();
+        let batches = Arc::new(AtomicUsize::new(0));
+        let positions = Arc::new(AtomicUsize::new(0));
+        let history_len = model.history_len();
+        let (batch_counter, position_counter) = (batches.clone(), positions.clone());
+        thread::spawn(move || {
+            Self::dispatch(&*model, &request_receiver, max_batch, max_wait, &batch_counter, &position_counter)
+        });
+        Self {
+            requests,
+            history_len,
+            batches,
+            positions,
+        }
+    }
+
+    // collects the waiting positions into batches until every sender is dropped
+    fn dispatch<M: PolicyValueModel + ?Sized>(
+        model: &M,
+        request_receiver: &Receiver<BatchRequest>,
+        max_batch: usize,
+        max_wait: Duration,
+        batch_counter: &AtomicUsize,
+        position_counter: &AtomicUsize,
+    ) {
+        while let Ok(first) = request_receiver.recv() {
+            let deadline = Instant::now() + max_wait;
+            let mut requests = vec![first];
+            while requests.len() < max_batch {
+                match request_receiver.recv_deadline(deadline) {
+                    Ok(request) => requests.push(request),
+                    Err(_) => break,
+                }
+            }
+            let (positions, replies): (Vec<Position>, Vec<(usize, Reply)>) = requests
+                .into_iter()
+                .map(|request| (request.position, (request.index, request.reply)))
+                .unzip();
+            let predictions = model.predict(&positions);
+            batch_counter.fetch_add(1, Ordering::Relaxed);
+            position_counter.fetch_add(positions.len(), Ordering::Relaxed);
+            for ((index, reply), prediction) in replies.into_iter().zip(predictions) {
+                // the caller may have given up waiting, so a failed reply is ignored
+                let _ = reply.send((index, prediction));
+            }
+        }
+    }
+
+    /// returns the number of batches and positions predicted so far
+    pub fn stats(&self) -> BatchStats {
+        BatchStats {
+            batches: self.batches.load(Ordering::Relaxed),
+            positions: self.positions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl PolicyValueModel for BatchedModel {
+    /// # Panics
+    ///
+    /// will panic if the dispatcher thread stopped because the wrapped model panicked
+    fn predict(&self, positions: &[Position]) -> Vec<Prediction> {
+        let (reply, reply_receiver) = bounded(positions.len());
+        for (index, position) in positions.iter().enumerate() {
+            let request = BatchRequest {
+                position: position.clone(),
+                index,
+                reply: reply.clone(),
+            };
+            self.requests.send(request).expect("the dispatcher of the batched model stopped");
+        }
+        drop(reply);
+        let mut predictions: Vec<Option<Prediction>> = vec![None; positions.len()];
+        for (index, prediction) in reply_receiver.iter() {
+            predictions[index] = Some(prediction);
+        }
+        predictions
+            .into_iter()
+            .map(|prediction| prediction.expect("the dispatcher of the batched model stopped"))
+            .collect()
+    }
+
+    fn history_len(&self) -> usize {
+        self.history_len
+    }
+}
@@ -0,0 +1,436 @@
+use rand::distributions::WeightedIndex;
+use rand::prelude::{Distribution, Rng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::evaluator::Evaluator;
+use super::game::{AbaloneGame, Board};
+use super::player::{MagisterLudi, SearchBudget};
+use super::selfplay::SelfPlayGame;
+use super::training::Symmetry;
+
+/// default number of moves from the start of a game that are added to a book
+pub const DEFAULT_BOOK_MOVES: usize = 12;
+
+/// default number of positions `BookBuilder::add_search` searches at most
+pub const DEFAULT_BOOK_SEARCHES: usize = 1000;
+
+// field code of the fields outside of the board, they are not part of the position keys
+const OFF_BOARD: i8 = 3;
+
+/// error while reading or writing an opening book
+#[derive(Debug)]
+pub enum BookError {
+    /// the book file could not be read or written
+    Io(io::Error),
+    /// the book file is not valid JSON
+    Json(String),
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access opening book: {err}"),
+            Self::Json(msg) => write!(f, "invalid opening book: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for BookError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Json(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for BookError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// move of an opening book position
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookMove {
+    /// ID of the move, see `marble_moves::MOVES_IDX`
+    pub move_id: usize,
+    /// weight of the move, moves are played with a probability proportional to their weight
+    pub weight: f64,
+}
+
+/// returns the canonical form of a position and the symmetry that maps the position to it
+///
+/// positions that only differ by a rotation or reflection of the board share their canonical form,
+/// which is the smallest of the 12 transformed boards.
+///
+/// # Arguments
+///
+/// * `pov_board` - position from the point of view of the side to move, see `AbaloneGame::get_rotated_state`
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::book::canonical_position;
+/// use rustai_abalone::game::GERMAN_DAISY;
+/// use rustai_abalone::training::Symmetry;
+/// let rotated = Symmetry { rotation: 1, reflect: false }.transform_board(&GERMAN_DAISY);
+/// let (canonical, symmetry) = canonical_position(&rotated);
+/// assert_eq!(canonical_position(&GERMAN_DAISY).0, canonical);
+/// assert_eq!(symmetry.transform_board(&rotated), canonical);
+/// ```
+pub fn canonical_position(pov_board: &Board) -> (Board, Symmetry) {
+    Symmetry::ALL
+        .iter()
+        .map(|symmetry| (symmetry.transform_board(pov_board), *symmetry))
+        .min_by(|first, second| first.0.cmp(&second.0))
+        .unwrap()
+}
+
+// maps a move of a canonical position to the smallest ID among the moves that are equivalent
+// because the position is symmetric, so these moves share their entry in the book
+fn canonical_move(canonical: &Board, move_id: usize) -> usize {
+    Symmetry::ALL
+        .iter()
+        .filter(|symmetry| symmetry.transform_board(canonical) == *canonical)
+        .map(|symmetry| symmetry.transform_move_id(move_id))
+        .min()
+        .unwrap()
+}
+
+// key of a canonical position in the book file, one digit for every field of the board row by row
+fn position_key(canonical: &Board) -> String {
+    canonical
+        .iter()
+        .flatten()
+        .filter(|field| **field != OFF_BOARD)
+        .map(|field| char::from(b'0' + *field as u8))
+        .collect()
+}
+
+// returns the position from the point of view of the side to move
+fn pov_board(board: &Board, black_tomove: bool) -> Board {
+    if black_tomove {
+        AbaloneGame::rotate_board(*board)
+    } else {
+        *board
+    }
+}
+
+/// weighted moves for positions of the opening, keyed by position with symmetry handling
+///
+/// every position is stored once in its canonical form from the point of view of the side to move,
+/// see `canonical_position`, so a book built from games on one side of the board also covers
+/// the rotated and reflected games. Books are stored as JSON and created with `BookBuilder`.
+/// An agent plays from a book with `player::MagisterLudi::set_opening_book`.
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::book::{BookBuilder, BookOptions};
+/// use rustai_abalone::game::{AbaloneGame, BELGIAN_DAISY};
+/// let mut game = AbaloneGame::new(BELGIAN_DAISY);
+/// game.calc_reasonalbe_moves();
+/// game.update_by_id(0);
+/// let mut builder = BookBuilder::new(BookOptions::default());
+/// assert_eq!(builder.add_game(game.get_state_history()), 1);
+/// let book = builder.build();
+/// assert_eq!(book.moves(&BELGIAN_DAISY, true).len(), 1);
+/// assert!(book.moves(&game.get_state(), false).is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpeningBook {
+    /// moves of every position sorted by weight, the keys contain the field codes of the
+    /// canonical position row by row without the fields outside of the board
+    pub positions: BTreeMap<String, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    /// returns the number of positions in the book
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// returns true if the book contains no position
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// returns the book moves for a position, the move IDs are transformed to the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - position as it is on the board (not rotated)
+    /// * `black_tomove` - if true, black is to move in the given position, otherwise white
+    ///
+    /// # Returns
+    ///
+    /// * `moves` - weighted moves from the point of view of the side to move, empty if the position is not in the book
+    pub fn moves(&self, board: &Board, black_tomove: bool) -> Vec<BookMove> {
+        let (canonical, symmetry) = canonical_position(&pov_board(board, black_tomove));
+        let inverse = symmetry.inverse();
+        self.positions
+            .get(&position_key(&canonical))
+            .map(|moves| {
+                moves
+                    .iter()
+                    .map(|book_move| BookMove {
+                        move_id: inverse.transform_move_id(book_move.move_id),
+                        weight: book_move.weight,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// draws a book move for a position with a probability proportional to its weight
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - position as it is on the board (not rotated)
+    /// * `black_tomove` - if true, black is to move in the given position, otherwise white
+    /// * `rng` - random number generator for drawing the move
+    ///
+    /// # Returns
+    ///
+    /// * `move_id` - ID of the drawn move from the point of view of the side to move,
+    ///   None if the position is not in the book
+    pub fn choose<R: Rng + ?Sized>(&self, board: &Board, black_tomove: bool, rng: &mut R) -> Option<usize> {
+        let moves = self.moves(board, black_tomove);
+        let weights = WeightedIndex::new(moves.iter().map(|book_move| book_move.weight)).ok()?;
+        Some(moves[weights.sample(rng)].move_id)
+    }
+
+    /// reads a book that was saved with `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BookError> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|err| BookError::Json(err.to_string()))
+    }
+
+    /// saves the book as JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BookError> {
+        let content = serde_json::to_string_pretty(self).map_err(|err| BookError::Json(err.to_string()))?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// options for building an opening book
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BookOptions {
+    /// number of moves from the start of a game that are added to the book
+    pub max_moves: usize,
+    /// positions with a lower total weight are left out, every added game or search adds
+    /// a weight of 1 to each of its positions
+    pub min_position_weight: f64,
+    /// moves with a lower share of the weight of their position are left out
+    pub min_move_share: f64,
+    /// maximum number of positions one call of `BookBuilder::add_search` searches
+    pub max_searches: usize,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        Self {
+            max_moves: DEFAULT_BOOK_MOVES,
+            min_position_weight: 1.0,
+            min_move_share: 0.05,
+            max_searches: DEFAULT_BOOK_SEARCHES,
+        }
+    }
+}
+
+/// collects weighted moves from stored games and searches and builds an `OpeningBook`
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use rustai_abalone::book::{BookBuilder, BookOptions};
+/// use rustai_abalone::config::AgentConfig;
+/// use rustai_abalone::evaluator::HeuristicEvaluator;
+/// use rustai_abalone::game::BELGIAN_DAISY;
+/// use rustai_abalone::player::{MagisterLudi, SearchBudget};
+/// let config = AgentConfig::builder().mcts_num(20).mcts_parallel(2).mcts_depth(3).build().unwrap();
+/// let mut agent = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+/// let options = BookOptions { max_moves: 2, ..Default::default() };
+/// let mut builder = BookBuilder::new(options);
+/// // the start position and the positions after the two best moves, unless they are symmetric
+/// let searched = builder.add_search(&mut agent, BELGIAN_DAISY, SearchBudget::new(20, 2));
+/// assert!((2..=3).contains(&searched));
+/// let book = builder.build();
+/// assert!(!book.moves(&BELGIAN_DAISY, true).is_empty());
+/// # agent.stop_execution();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BookBuilder {
+    options: BookOptions,
+    // summed weight of every move per canonical position
+    positions: HashMap<Board, BTreeMap<usize, f64>>,
+}
+
+impl BookBuilder {
+    /// creates an empty builder
+    pub fn new(options: BookOptions) -> Self {
+        Self {
+            options,
+            positions: HashMap::new(),
+        }
+    }
+
+    /// adds a weighted move of a position
+    ///
+    /// # Arguments
+    ///
+    /// * `pov_board` - position from the point of view of the side to move, see `AbaloneGame::get_rotated_state`
+    /// * `move_id` - ID of the move in this position, see `marble_moves::MOVES_IDX`
+    /// * `weight` - weight that is added to the move
+    pub fn add_move(&mut self, pov_board: &Board, move_id: usize, weight: f64) {
+        let (canonical, symmetry) = canonical_position(pov_board);
+        let move_id = canonical_move(&canonical, symmetry.transform_move_id(move_id));
+        *self.positions.entry(canonical).or_default().entry(move_id).or_insert(0.0) += weight;
+    }
+
+    /// adds the first moves of a stored game with a weight of 1 each
+    ///
+    /// # Arguments
+    ///
+    /// * `history` - states of the game as they are on the board, the starting position first,
+    ///   see `AbaloneGame::get_state_history`. Black moves first
+    ///
+    /// # Returns
+    ///
+    /// * `moves` - number of added moves, the game is only read up to the first state that
+    ///   cannot be reached by a move
+    pub fn add_game(&mut self, history: &[Board]) -> usize {
+        let Some(start) = history.first() else {
+            return 0;
+        };
+        let mut game = AbaloneGame::new(*start);
+        let mut added = 0;
+        for next_state in history.iter().skip(1).take(self.options.max_moves) {
+            if game.get_game_ended() {
+                break;
+            }
+            let (pov_state, move_ids) = game.calc_reasonalbe_moves();
+            // follow-up positions are from the point of view of the side to move
+            let next_pov = pov_board(next_state, game.get_black_tomove());
+            let Some(idx) = (0..move_ids.len()).find(|idx| game.get_next_position(*idx) == next_pov) else {
+                break;
+            };
+            self.add_move(&pov_state, move_ids[idx], 1.0);
+            game.update_by_id(idx);
+            added += 1;
+        }
+        added
+    }
+
+    /// adds the visit distributions of the searched positions within the first `max_moves` moves of a self-play game
+    ///
+    /// # Returns
+    ///
+    /// * `positions` - number of added positions
+    pub fn add_self_play(&mut self, game: &SelfPlayGame) -> usize {
+        let max_moves = self.options.max_moves;
+        let mut added = 0;
+        // positions after moves of an opening book are recorded with their move number
+        for record in game.records.iter().filter(|record| record.move_number < max_moves) {
+            for (move_id, share) in record.policy.iter().enumerate() {
+                if *share > 0.0 {
+                    self.add_move(&record.board, move_id, f64::from(*share));
+                }
+            }
+            added += 1;
+        }
+        added
+    }
+
+    /// searches the opening with the agent and adds the visit shares of the candidate moves
+    ///
+    /// starting with the given position, every position that is reached by a candidate move
+    /// with at least `min_move_share` of the visits is searched as well until `max_moves` moves
+    /// were made, positions that only differ by symmetry are searched once. The positions are
+    /// searched move by move and the search stops after `max_searches` positions.
+    ///
+    /// every search costs `budget.mcts_num` simulations. Without the limit the number of searches
+    /// could grow with `budget.multi_pv` to the power of `max_moves`, so a search to the default
+    /// depth with three candidates per position takes `max_searches` searches and only reaches
+    /// the first few moves. Deep books need a small number of candidates.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent` - agent that searches the positions, the game it is playing is not changed
+    /// * `start_board` - starting position of the opening, black moves first
+    /// * `budget` - number of leafs per search and number of candidate moves per position
+    ///
+    /// # Returns
+    ///
+    /// * `positions` - number of searched positions
+    ///
+    /// # Panics
+    ///
+    /// will panic if one or more of the MCTS daemon threads of the agent are not active anymore
+    pub fn add_search<M: Evaluator + ?Sized + 'static>(
+        &mut self,
+        agent: &mut MagisterLudi<M>,
+        start_board: Board,
+        budget: SearchBudget,
+    ) -> usize {
+        let mut searched: HashSet<Board> = HashSet::new();
+        let mut frontier = vec![(start_board, true)];
+        for depth in 0..self.options.max_moves {
+            let mut next_frontier = Vec::new();
+            for (board, black_tomove) in frontier {
+                if searched.len() >= self.options.max_searches {
+                    return searched.len();
+                }
+                let pov_state = pov_board(&board, black_tomove);
+                if !searched.insert(canonical_position(&pov_state).0) {
+                    continue;
+                }
+                let report = agent.analyse(board, black_tomove, depth, budget);
+                let total: usize = report.children.iter().map(|child| child.visits).sum();
+                for child in report.children.iter() {
+                    let share = child.visits as f64 / total.max(1) as f64;
+                    self.add_move(&pov_state, child.move_id, share);
+                    // moves that are left out of the book are not followed
+                    if depth + 1 < self.options.max_moves && share >= self.options.min_move_share {
+                        next_frontier.push((pov_board(&child.board, black_tomove), !black_tomove));
+                    }
+                }
+            }
+            frontier = next_frontier;
+        }
+        searched.len()
+    }
+
+    /// returns the book with all positions and moves that pass the thresholds of the options
+    pub fn build(&self) -> OpeningBook {
+        let mut positions = BTreeMap::new();
+        for (canonical, moves) in self.positions.iter() {
+            let total: f64 = moves.values().sum();
+            if total <= 0.0 || total < self.options.min_position_weight {
+                continue;
+            }
+            let mut book_moves: Vec<BookMove> = moves
+                .iter()
+                .filter(|(_, weight)| **weight / total >= self.options.min_move_share)
+                .map(|(move_id, weight)| BookMove {
+                    move_id: *move_id,
+                    weight: *weight,
+                })
+                .collect();
+            if book_moves.is_empty() {
+                continue;
+            }
+            book_moves.sort_by(|first, second| second.weight.total_cmp(&first.weight));
+            positions.insert(position_key(canonical), book_moves);
+        }
+        OpeningBook { positions }
+    }
+}
@@ -0,0 +1,308 @@
+use rand::distributions::WeightedIndex;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use super::game::Board;
+
+/// default memory the distribution cache is allowed to occupy, 256 MiB
+pub const DEFAULT_CACHE_MEMORY: usize = 256 * 1024 * 1024;
+
+/// number of independently locked shards, so threads rarely wait on each other
+const NUM_SHARDS: usize = 16;
+/// rough estimate of the memory one cached position occupies: the board itself,
+/// the probabilities and cumulative weights for around 100 moves and the hash map overhead
+const ENTRY_BYTES: usize = 1024;
+
+/// hit and miss statistics of a `DistributionCache`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// number of lookups which found a stored distribution
+    pub hits: u64,
+    /// number of lookups which did not find a stored distribution
+    pub misses: u64,
+    /// number of distributions that were dropped to stay within the memory cap
+    pub evictions: u64,
+    /// number of distributions currently stored
+    pub entries: usize,
+}
+
+impl CacheStats {
+    /// returns the share of lookups which found a stored distribution
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::cache::CacheStats;
+    /// let stats = CacheStats { hits: 3, misses: 1, evictions: 0, entries: 3 };
+    /// assert_eq!(stats.hit_rate(), 0.75);
+    /// ```
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / lookups as f64
+    }
+}
+
+// one part of the cache with its own lock. Entries are kept in two generations:
+// new entries go into `current` and once it is full the `previous` generation
+// is dropped and replaced by the current one. Entries found in `previous` are
+// moved back into `current`, so frequently visited positions survive.
+struct Shard {
+    current: HashMap<Board, Entry>,
+    previous: HashMap<Board, Entry>,
+}
+
+// the probabilities of the moves and the distribution drawing from them
+struct Entry {
+    priors: Vec<f32>,
+    distr: WeightedIndex<f32>,
+}
+
+/// size-bounded cache for the move distributions of visited positions
+///
+/// calculating a distribution requires a run of the neural network, so they are
+/// stored and shared between all MCTS threads. The cache is split into shards
+/// that are locked independently and it evicts the least recently used generation
+/// of positions once the configured memory cap is reached.
+pub struct DistributionCache {
+    shards: Vec<Mutex<Shard>>,
+    hasher: RandomState,
+    /// maximum number of entries per generation and shard
+    generation_capacity: AtomicUsize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl DistributionCache {
+    /// creates an empty cache that will approximately not exceed the given memory
+    ///
+    /// # Arguments
+    ///
+    /// * `memory_cap` - maximum memory in bytes the cached distributions may occupy
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::cache::DistributionCache;
+    /// let cache = DistributionCache::new(64 * 1024 * 1024);
+    /// assert_eq!(cache.stats().entries, 0);
+    /// ```
+    pub fn new(memory_cap: usize) -> Self {
+        let shards = (0..NUM_SHARDS)
+            .map(|_| {
+                Mutex::new(Shard {
+                    current: HashMap::new(),
+                    previous: HashMap::new(),
+                })
+            })
+            .collect();
+        Self {
+            shards,
+            hasher: RandomState::new(),
+            generation_capacity: AtomicUsize::new(Self::generation_capacity(memory_cap)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    // every shard holds up to two generations
+    fn generation_capacity(memory_cap: usize) -> usize {
+        (memory_cap / ENTRY_BYTES / (2 * NUM_SHARDS)).max(1)
+    }
+
+    fn shard(&self, board: &Board) -> &Mutex<Shard> {
+        let idx = self.hasher.hash_one(board) as usize % NUM_SHARDS;
+        &self.shards[idx]
+    }
+
+    /// returns the move probabilities stored for a position
+    ///
+    /// unlike `get_with` the lookup is not counted as hit or miss and does not
+    /// keep the position in the cache for longer
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - position from white's point of view
+    ///
+    /// # Returns
+    ///
+    /// * `priors` - probabilities of the reasonable moves or None if the position is not cached
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::cache::DistributionCache;
+    /// use rustai_abalone::game::BELGIAN_DAISY;
+    /// let cache = DistributionCache::new(1024 * 1024);
+    /// cache.insert(BELGIAN_DAISY, vec![0.25, 0.75]);
+    /// assert_eq!(cache.peek_priors(&BELGIAN_DAISY), Some(vec![0.25, 0.75]));
+    /// assert_eq!(cache.stats().hits, 0);
+    /// ```
+    pub fn peek_priors(&self, board: &Board) -> Option<Vec<f32>> {
+        let shard = self.shard(board).lock().unwrap();
+        shard
+            .current
+            .get(board)
+            .or_else(|| shard.previous.get(board))
+            .map(|entry| entry.priors.clone())
+    }
+
+    /// looks up the distribution for a position and applies the given function to it
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - position from white's point of view
+    /// * `func` - function that is applied to the stored distribution, e.g. to draw a sample
+    ///
+    /// # Returns
+    ///
+    /// * `result` - result of `func` or None if the position is not cached
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rand::distributions::Distribution;
+    /// use rustai_abalone::cache::DistributionCache;
+    /// use rustai_abalone::game::BELGIAN_DAISY;
+    /// let cache = DistributionCache::new(1024 * 1024);
+    /// cache.insert(BELGIAN_DAISY, vec![0.25, 0.75]);
+    /// let sample = cache.get_with(&BELGIAN_DAISY, |distr| distr.sample(&mut rand::thread_rng()));
+    /// assert!(sample.unwrap() < 2);
+    /// ```
+    pub fn get_with<R>(&self, board: &Board, func: impl FnOnce(&WeightedIndex<f32>) -> R) -> Option<R> {
+        let mut shard = self.shard(board).lock().unwrap();
+        let result = if let Some(entry) = shard.current.get(board) {
+            Some(func(&entry.distr))
+        } else if let Some(entry) = shard.previous.remove(board) {
+            // the position is still in use, so keep it for the next generation
+            let result = func(&entry.distr);
+            self.insert_into(&mut shard, *board, entry);
+            Some(result)
+        } else {
+            None
+        };
+        match result {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        result
+    }
+
+    /// stores the move probabilities for a position and the distribution drawing from them
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - position from white's point of view
+    /// * `priors` - probabilities of the reasonable moves of that position
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::cache::DistributionCache;
+    /// use rustai_abalone::game::BELGIAN_DAISY;
+    /// let cache = DistributionCache::new(1024 * 1024);
+    /// cache.insert(BELGIAN_DAISY, vec![0.25, 0.75]);
+    /// assert_eq!(cache.stats().entries, 1);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the probabilities are empty, negative or all zero
+    pub fn insert(&self, board: Board, priors: Vec<f32>) {
+        let distr = WeightedIndex::new(&priors).unwrap();
+        let mut shard = self.shard(&board).lock().unwrap();
+        self.insert_into(&mut shard, board, Entry { priors, distr });
+    }
+
+    fn insert_into(&self, shard: &mut Shard, board: Board, entry: Entry) {
+        if shard.current.len() >= self.generation_capacity.load(Ordering::Relaxed) {
+            let dropped = std::mem::take(&mut shard.previous);
+            self.evictions.fetch_add(dropped.len() as u64, Ordering::Relaxed);
+            shard.previous = std::mem::take(&mut shard.current);
+        }
+        shard.current.insert(board, entry);
+    }
+
+    /// changes the memory cap of the cache
+    ///
+    /// the cache does not shrink immediately, but evicts older positions
+    /// with the next insertions
+    ///
+    /// # Arguments
+    ///
+    /// * `memory_cap` - maximum memory in bytes the cached distributions may occupy
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rustai_abalone::cache::DistributionCache;
+    /// # let cache = DistributionCache::new(1024 * 1024);
+    /// cache.set_memory_cap(32 * 1024 * 1024);
+    /// ```
+    pub fn set_memory_cap(&self, memory_cap: usize) {
+        self.generation_capacity
+            .store(Self::generation_capacity(memory_cap), Ordering::Relaxed);
+    }
+
+    /// removes all stored distributions and resets the statistics
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rustai_abalone::cache::DistributionCache;
+    /// # let cache = DistributionCache::new(1024 * 1024);
+    /// cache.clear();
+    /// assert_eq!(cache.stats().hits, 0);
+    /// ```
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            let mut shard = shard.lock().unwrap();
+            shard.current.clear();
+            shard.previous.clear();
+        }
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+        self.evictions.store(0, Ordering::Relaxed);
+    }
+
+    /// returns the hit and miss statistics and the number of stored distributions
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rustai_abalone::cache::DistributionCache;
+    /// # use rustai_abalone::game::BELGIAN_DAISY;
+    /// let cache = DistributionCache::new(1024 * 1024);
+    /// assert!(cache.get_with(&BELGIAN_DAISY, |_| ()).is_none());
+    /// assert_eq!(cache.stats().misses, 1);
+    /// ```
+    pub fn stats(&self) -> CacheStats {
+        let entries = self
+            .shards
+            .iter()
+            .map(|shard| {
+                let shard = shard.lock().unwrap();
+                shard.current.len() + shard.previous.len()
+            })
+            .sum();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries,
+        }
+    }
+}
+
+impl Default for DistributionCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_MEMORY)
+    }
+}
@@ -0,0 +1,544 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::cache::DEFAULT_CACHE_MEMORY;
+use super::encoder::InputEncoding;
+use super::player::MoveSelection;
+
+/// names of the input and outputs in the `serving_default` signature of the tensorflow model
+/// and the encoding of its input
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::config::AgentConfig;
+/// use rustai_abalone::encoder::InputEncoding;
+/// let config = AgentConfig::from_toml(r#"
+///     [signature]
+///     input = "boards"
+///     encoding = { type = "history", history_len = 2, side_to_move = true, move_counter = false }
+/// "#).unwrap();
+/// assert_eq!(config.signature.policy_output, "pol_prediction");
+/// assert!(matches!(config.signature.encoding, InputEncoding::History { history_len: 2, .. }));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelSignature {
+    /// name of the input for the encoded board
+    pub input: String,
+    /// name of the output for the move logits
+    pub policy_output: String,
+    /// name of the output for the position evaluation
+    pub value_output: String,
+    /// encoding of the positions the model expects as input
+    pub encoding: InputEncoding,
+}
+
+impl Default for ModelSignature {
+    fn default() -> Self {
+        Self {
+            input: "input_8".to_string(),
+            policy_output: "pol_prediction".to_string(),
+            value_output: "val_prediction".to_string(),
+            encoding: InputEncoding::OneHot,
+        }
+    }
+}
+
+/// error for invalid agent configurations or configuration files that cannot be read
+#[derive(Debug)]
+pub enum ConfigError {
+    /// a parameter has a value that is not allowed, the message explains which one
+    Invalid(String),
+    /// the configuration file could not be read
+    Io(std::io::Error),
+    /// the configuration file is not valid TOML or JSON
+    Parse(String),
+    /// the file extension is neither `.toml` nor `.json`
+    UnknownFormat(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(msg) => write!(f, "invalid agent configuration: {msg}"),
+            Self::Io(err) => write!(f, "could not read agent configuration: {err}"),
+            Self::Parse(msg) => write!(f, "could not parse agent configuration: {msg}"),
+            Self::UnknownFormat(ext) => write!(f, "unknown configuration format '{ext}', use .toml or .json"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// configuration of a `MagisterLudi` agent
+///
+/// missing fields in configuration files are filled with the default values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgentConfig {
+    /// path to the stored tensorflow model, if None the default model of the per-user
+    /// model store and the signature of its manifest are used, see `store::ModelStore::default_model`
+    pub model_path: Option<String>,
+    /// names of the model's input and outputs
+    pub signature: ModelSignature,
+    /// number of leafs for every MCTS
+    pub mcts_num: usize,
+    /// number of threads for the MCTS, if 0 the simulations run on the calling thread
+    pub mcts_parallel: usize,
+    /// denotes how often a child state must at least be selected
+    pub mcts_minimum: usize,
+    /// number of moves that will be simulated starting for the root state,
+    /// if 0 simulations run until the games end
+    pub mcts_depth: usize,
+    /// maximum memory in bytes the cache for move distributions may occupy
+    pub cache_memory: usize,
+    /// interval in milliseconds in which the agent checks that its MCTS threads are alive while waiting for results
+    pub thread_check_millis: u64,
+    /// seed for the random number generators, if None they are seeded from system entropy
+    pub seed: Option<u64>,
+    /// options for selecting the simulated positions and the move to play
+    pub move_selection: MoveSelection,
+    /// the agent resigns if the value of its best move stays below this threshold,
+    /// if None the agent never resigns
+    pub resign_threshold: Option<f32>,
+    /// number of consecutive own moves below the resignation threshold before the agent resigns
+    pub resign_moves: usize,
+    /// the agent offers and accepts draws if the absolute value of its best move stays
+    /// within this threshold, if None the agent neither offers nor accepts draws
+    pub draw_threshold: Option<f32>,
+    /// number of consecutive own moves within the draw threshold before the agent offers a draw
+    pub draw_moves: usize,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            model_path: None,
+            signature: ModelSignature::default(),
+            mcts_num: 200,
+            mcts_parallel: 10,
+            mcts_minimum: 1,
+            mcts_depth: 13,
+            cache_memory: DEFAULT_CACHE_MEMORY,
+            thread_check_millis: 1000,
+            seed: None,
+            move_selection: MoveSelection::default(),
+            resign_threshold: None,
+            resign_moves: 3,
+            draw_threshold: None,
+            draw_moves: 10,
+        }
+    }
+}
+
+impl AgentConfig {
+    /// returns a builder starting from the default configuration
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::AgentConfig;
+    /// let config = AgentConfig::builder()
+    ///     .mcts_num(100)
+    ///     .mcts_parallel(4)
+    ///     .mcts_depth(10)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(config.mcts_num, 100);
+    /// ```
+    pub fn builder() -> AgentConfigBuilder {
+        AgentConfigBuilder::default()
+    }
+
+    /// checks whether all parameters are in their allowed ranges
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::AgentConfig;
+    /// let mut config = AgentConfig::default();
+    /// assert!(config.validate().is_ok());
+    /// config.mcts_minimum = config.mcts_num + 1;
+    /// assert!(config.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.mcts_num == 0 {
+            return Err(ConfigError::Invalid("mcts_num must be at least 1".to_string()));
+        }
+        if self.mcts_minimum > self.mcts_num {
+            return Err(ConfigError::Invalid(format!(
+                "mcts_minimum ({}) must not exceed mcts_num ({})",
+                self.mcts_minimum, self.mcts_num
+            )));
+        }
+        if self.cache_memory == 0 {
+            return Err(ConfigError::Invalid("cache_memory must be positive".to_string()));
+        }
+        if self.thread_check_millis == 0 {
+            return Err(ConfigError::Invalid("thread_check_millis must be positive".to_string()));
+        }
+        if self.resign_threshold.is_some_and(|threshold| !(-1.0..=1.0).contains(&threshold)) {
+            return Err(ConfigError::Invalid("resign_threshold must be between -1 and 1".to_string()));
+        }
+        if self.draw_threshold.is_some_and(|threshold| !(0.0..=1.0).contains(&threshold)) {
+            return Err(ConfigError::Invalid("draw_threshold must be between 0 and 1".to_string()));
+        }
+        if self.resign_moves == 0 || self.draw_moves == 0 {
+            return Err(ConfigError::Invalid("resign_moves and draw_moves must be at least 1".to_string()));
+        }
+        let signature = &self.signature;
+        if signature.input.is_empty() || signature.policy_output.is_empty() || signature.value_output.is_empty() {
+            return Err(ConfigError::Invalid("signature names must not be empty".to_string()));
+        }
+        let selection = &self.move_selection;
+        if !(0.0..=1.0).contains(&selection.dirichlet_epsilon) {
+            return Err(ConfigError::Invalid("dirichlet_epsilon must be between 0 and 1".to_string()));
+        }
+        if selection.dirichlet_epsilon > 0.0 && selection.dirichlet_alpha <= 0.0 {
+            return Err(ConfigError::Invalid("dirichlet_alpha must be positive if noise is used".to_string()));
+        }
+        if selection.temperature_schedule.iter().any(|(_, temperature)| *temperature < 0.0) {
+            return Err(ConfigError::Invalid("temperatures must not be negative".to_string()));
+        }
+        if !(0.0..=1.0).contains(&selection.blunder_probability) {
+            return Err(ConfigError::Invalid("blunder_probability must be between 0 and 1".to_string()));
+        }
+        if selection.temperature_schedule.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(ConfigError::Invalid("temperature_schedule must be sorted by move number".to_string()));
+        }
+        Ok(())
+    }
+
+    /// checks whether every follow-up position can be selected `mcts_minimum` times
+    ///
+    /// this depends on the position, so it is checked for every search. If the check
+    /// fails, the agent lowers the minimum to `mcts_num / num_children` for that search
+    ///
+    /// # Arguments
+    ///
+    /// * `num_children` - number of follow-up positions of the searched position
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::AgentConfig;
+    /// let config = AgentConfig::builder().mcts_num(100).mcts_minimum(2).build().unwrap();
+    /// assert!(config.validate_children(50).is_ok());
+    /// assert!(config.validate_children(51).is_err());
+    /// ```
+    pub fn validate_children(&self, num_children: usize) -> Result<(), ConfigError> {
+        if self.mcts_minimum * num_children > self.mcts_num {
+            return Err(ConfigError::Invalid(format!(
+                "mcts_minimum ({}) * children ({}) exceeds mcts_num ({})",
+                self.mcts_minimum, num_children, self.mcts_num
+            )));
+        }
+        Ok(())
+    }
+
+    /// reads and validates a configuration from a `.toml` or `.json` file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path to the configuration file
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::AgentConfig;
+    /// let path = std::env::temp_dir().join("rustai_abalone_doc_agent.toml");
+    /// std::fs::write(&path, "mcts_num = 50\nmcts_parallel = 2\n").unwrap();
+    /// let config = AgentConfig::from_file(&path).unwrap();
+    /// assert_eq!(config.mcts_num, 50);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            Some("json") => Self::from_json(&content),
+            other => Err(ConfigError::UnknownFormat(other.unwrap_or_default().to_string())),
+        }
+    }
+
+    /// parses and validates a configuration in TOML format
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::AgentConfig;
+    /// let config = AgentConfig::from_toml("mcts_num = 80\n[move_selection]\ngreedy = true\n").unwrap();
+    /// assert!(config.move_selection.greedy);
+    /// ```
+    pub fn from_toml(content: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(content).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// parses and validates a configuration in JSON format
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::AgentConfig;
+    /// let config = AgentConfig::from_json(r#"{"mcts_num": 80, "seed": 7}"#).unwrap();
+    /// assert_eq!(config.seed, Some(7));
+    /// ```
+    pub fn from_json(content: &str) -> Result<Self, ConfigError> {
+        let config: Self = serde_json::from_str(content).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// builder for an `AgentConfig`, see `AgentConfig::builder`
+#[derive(Debug, Clone, Default)]
+pub struct AgentConfigBuilder {
+    config: AgentConfig,
+}
+
+impl AgentConfigBuilder {
+    /// sets the path to the stored tensorflow model
+    pub fn model_path(mut self, model_path: &str) -> Self {
+        self.config.model_path = Some(model_path.to_string());
+        self
+    }
+
+    /// sets the names of the model's input and outputs
+    pub fn signature(mut self, signature: ModelSignature) -> Self {
+        self.config.signature = signature;
+        self
+    }
+
+    /// sets the number of leafs for every MCTS
+    pub fn mcts_num(mut self, mcts_num: usize) -> Self {
+        self.config.mcts_num = mcts_num;
+        self
+    }
+
+    /// sets the number of threads for the MCTS
+    pub fn mcts_parallel(mut self, mcts_parallel: usize) -> Self {
+        self.config.mcts_parallel = mcts_parallel;
+        self
+    }
+
+    /// sets how often a child state must at least be selected
+    pub fn mcts_minimum(mut self, mcts_minimum: usize) -> Self {
+        self.config.mcts_minimum = mcts_minimum;
+        self
+    }
+
+    /// sets the number of moves that will be simulated starting for the root state
+    pub fn mcts_depth(mut self, mcts_depth: usize) -> Self {
+        self.config.mcts_depth = mcts_depth;
+        self
+    }
+
+    /// sets the maximum memory in bytes for the cache of move distributions
+    pub fn cache_memory(mut self, cache_memory: usize) -> Self {
+        self.config.cache_memory = cache_memory;
+        self
+    }
+
+    /// sets the interval in milliseconds for checking the MCTS threads
+    pub fn thread_check_millis(mut self, thread_check_millis: u64) -> Self {
+        self.config.thread_check_millis = thread_check_millis;
+        self
+    }
+
+    /// sets the seed for the random number generators
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// enables resignation if the value of the best move stays below `threshold` for `moves` consecutive own moves
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::AgentConfig;
+    /// let config = AgentConfig::builder().resign(-0.9, 3).build().unwrap();
+    /// assert_eq!(config.resign_threshold, Some(-0.9));
+    /// ```
+    pub fn resign(mut self, threshold: f32, moves: usize) -> Self {
+        self.config.resign_threshold = Some(threshold);
+        self.config.resign_moves = moves;
+        self
+    }
+
+    /// enables draw offers if the absolute value of the best move stays within `threshold`
+    /// for `moves` consecutive own moves, offers of the opponent are accepted within the same threshold
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::AgentConfig;
+    /// let config = AgentConfig::builder().draw(0.05, 20).build().unwrap();
+    /// assert_eq!(config.draw_moves, 20);
+    /// ```
+    pub fn draw(mut self, threshold: f32, moves: usize) -> Self {
+        self.config.draw_threshold = Some(threshold);
+        self.config.draw_moves = moves;
+        self
+    }
+
+    /// sets search budget and move selection to the values of a strength level,
+    /// model and threading options are kept
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::{AgentConfig, Difficulty};
+    /// let config = AgentConfig::builder().difficulty(Difficulty::Novice).mcts_parallel(2).build().unwrap();
+    /// assert_eq!(config.mcts_num, Difficulty::Novice.config().mcts_num);
+    /// ```
+    pub fn difficulty(mut self, difficulty: Difficulty) -> Self {
+        let level = difficulty.config();
+        self.config.mcts_num = level.mcts_num;
+        self.config.mcts_minimum = level.mcts_minimum;
+        self.config.mcts_depth = level.mcts_depth;
+        self.config.move_selection = level.move_selection;
+        self
+    }
+
+    /// sets the options for selecting the simulated positions and the move to play
+    pub fn move_selection(mut self, move_selection: MoveSelection) -> Self {
+        self.config.move_selection = move_selection;
+        self
+    }
+
+    /// validates and returns the configuration
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::AgentConfig;
+    /// assert!(AgentConfig::builder().mcts_num(0).build().is_err());
+    /// ```
+    pub fn build(self) -> Result<AgentConfig, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// named playing strengths for human opponents
+///
+/// every level combines a search budget, a probability for deliberately playing a random
+/// move and, for advanced, a temperature for choosing among the searched moves.
+/// The levels are ordered from weakest to strongest and are rated against each
+/// other with `cargo run --release --example difficulty_match`.
+///
+/// Measured with the `HeuristicEvaluator`, 40 games per pair of neighbouring levels
+/// and games adjudicated as draw after 300 moves:
+///
+/// | level        | rating    | result against the next weaker level |
+/// |--------------|-----------|--------------------------------------|
+/// | beginner     | 1165 ± 58 |                                      |
+/// | novice       | 1297 ± 42 | +17 =22 -1, +147 Elo                 |
+/// | intermediate | 1449 ± 45 | +23 =13 -4, +179 Elo                 |
+/// | advanced     | 1693 ± 52 | +31 =5 -4, +285 Elo                  |
+/// | master       | 2034 ± 83 | +36 =0 -4, +382 Elo                  |
+///
+/// The weak levels are set apart by their random moves. All levels search to the same
+/// depth, as a shallower search did not play weaker with this evaluator.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    /// very short search, plays a random move in more than half of its turns
+    Beginner,
+    /// short search, plays a random move in about a third of its turns
+    Novice,
+    /// medium search, plays a random move in every fifth turn
+    Intermediate,
+    /// full search, only varies its moves at the start of the game
+    Advanced,
+    /// doubled search budget and always plays the best move
+    Master,
+}
+
+impl Difficulty {
+    /// all levels from weakest to strongest
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Novice,
+        Difficulty::Intermediate,
+        Difficulty::Advanced,
+        Difficulty::Master,
+    ];
+
+    /// returns the default configuration with search budget and move selection of this level
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::Difficulty;
+    /// let config = Difficulty::Beginner.config();
+    /// assert!(config.move_selection.blunder_probability > 0.0);
+    /// assert!(config.mcts_num < Difficulty::Master.config().mcts_num);
+    /// ```
+    pub fn config(&self) -> AgentConfig {
+        let (mcts_num, mcts_depth, temperature_schedule, blunder_probability) = match self {
+            Self::Beginner => (10, 13, Vec::new(), 0.6),
+            Self::Novice => (25, 13, Vec::new(), 0.35),
+            Self::Intermediate => (60, 13, Vec::new(), 0.2),
+            Self::Advanced => (200, 13, vec![(0, 0.1), (20, 0.0)], 0.0),
+            Self::Master => (400, 13, Vec::new(), 0.0),
+        };
+        AgentConfig {
+            mcts_num,
+            // weak levels cannot afford to visit every move
+            mcts_minimum: usize::from(mcts_num >= 100),
+            mcts_depth,
+            move_selection: MoveSelection {
+                temperature_schedule,
+                blunder_probability,
+                ..MoveSelection::default()
+            },
+            ..AgentConfig::default()
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Beginner => "beginner",
+            Self::Novice => "novice",
+            Self::Intermediate => "intermediate",
+            Self::Advanced => "advanced",
+            Self::Master => "master",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|level| level.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ConfigError::Invalid(format!("unknown difficulty '{s}'")))
+    }
+}
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+
+use super::evaluator::Position;
+use super::game::BOARD_SIZE;
+
+/// number of moves that corresponds to a value of 1 in the move counter plane of `HistoryEncoder`
+const MOVE_COUNTER_SCALE: f32 = 100.0;
+
+/// turns positions into the input of a neural network
+///
+/// the input of a single position has the shape `[11, 11, planes]`, for a batch
+/// the inputs of the positions are stacked to `[positions, 11, 11, planes]`
+pub trait InputEncoder: Send + Sync {
+    /// returns the number of planes per board field
+    fn planes(&self) -> usize;
+
+    /// returns the number of previous boards the encoder uses
+    fn history_len(&self) -> usize {
+        0
+    }
+
+    /// writes the input for a single position
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - position from the point of view of the side to move
+    /// * `input` - zeroed slice of length `11 * 11 * planes`, ordered by x, y and plane
+    fn encode(&self, position: &Position, input: &mut [f32]);
+
+    /// returns the stacked input for a batch of positions
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - positions from the point of view of the side to move
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::encoder::{InputEncoder, OneHotEncoder};
+    /// use rustai_abalone::evaluator::Position;
+    /// use rustai_abalone::game::BELGIAN_DAISY;
+    /// let input = OneHotEncoder.encode_batch(&[Position::from_board(BELGIAN_DAISY)]);
+    /// assert_eq!(input.len(), 11 * 11 * 4);
+    /// ```
+    fn encode_batch(&self, positions: &[Position]) -> Vec<f32> {
+        let size = BOARD_SIZE * BOARD_SIZE * self.planes();
+        let mut input = vec![0.0; positions.len() * size];
+        for (position, chunk) in positions.iter().zip(input.chunks_mut(size)) {
+            self.encode(position, chunk);
+        }
+        input
+    }
+}
+
+/// one-hot encoding of the four field types (empty, own marble, opposing marble, off-board),
+/// the input of the magister_zero model
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct OneHotEncoder;
+
+impl InputEncoder for OneHotEncoder {
+    fn planes(&self) -> usize {
+        4
+    }
+
+    fn encode(&self, position: &Position, input: &mut [f32]) {
+        for (x, row) in position.board.iter().enumerate() {
+            for (y, field) in row.iter().enumerate() {
+                input[(x * BOARD_SIZE + y) * 4 + *field as usize] = 1.0;
+            }
+        }
+    }
+}
+
+/// one-hot encoding of the current board followed by planes for previous boards,
+/// the side to move and the number of moves
+///
+/// every previous board adds a plane for the own and one for the opposing marbles,
+/// both from the point of view of the side to move. Missing previous boards at the
+/// start of a game leave their planes empty. The side to move plane is 1 if black
+/// is to move and the move counter plane holds the number of moves divided by 100.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct HistoryEncoder {
+    /// number of previous boards
+    pub history_len: usize,
+    /// if true, a plane for the side to move is added
+    pub side_to_move: bool,
+    /// if true, a plane for the number of moves is added
+    pub move_counter: bool,
+}
+
+impl InputEncoder for HistoryEncoder {
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::encoder::{HistoryEncoder, InputEncoder};
+    /// let encoder = HistoryEncoder { history_len: 2, side_to_move: true, move_counter: false };
+    /// assert_eq!(encoder.planes(), 4 + 2 * 2 + 1);
+    /// ```
+    fn planes(&self) -> usize {
+        4 + 2 * self.history_len + usize::from(self.side_to_move) + usize::from(self.move_counter)
+    }
+
+    fn history_len(&self) -> usize {
+        self.history_len
+    }
+
+    fn encode(&self, position: &Position, input: &mut [f32]) {
+        let planes = self.planes();
+        let side_plane = 4 + 2 * self.history_len;
+        let counter_plane = side_plane + usize::from(self.side_to_move);
+        for x in 0..BOARD_SIZE {
+            for y in 0..BOARD_SIZE {
+                let field = &mut input[(x * BOARD_SIZE + y) * planes..][..planes];
+                field[position.board[x][y] as usize] = 1.0;
+                for (idx, previous) in position.history.iter().take(self.history_len).enumerate() {
+                    match previous[x][y] {
+                        1 => field[4 + 2 * idx] = 1.0,
+                        2 => field[5 + 2 * idx] = 1.0,
+                        _ => (),
+                    }
+                }
+                if self.side_to_move && position.black_tomove {
+                    field[side_plane] = 1.0;
+                }
+                if self.move_counter {
+                    field[counter_plane] = position.num_moves as f32 / MOVE_COUNTER_SCALE;
+                }
+            }
+        }
+    }
+}
+
+/// input encoding of a model as it is stored in configuration files
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::encoder::{InputEncoder, InputEncoding};
+/// let encoding = InputEncoding::History { history_len: 4, side_to_move: true, move_counter: true };
+/// assert_eq!(encoding.encoder().history_len(), 4);
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputEncoding {
+    /// see `OneHotEncoder`
+    #[default]
+    OneHot,
+    /// see `HistoryEncoder`
+    History {
+        /// number of previous boards
+        history_len: usize,
+        /// if true, a plane for the side to move is added
+        side_to_move: bool,
+        /// if true, a plane for the number of moves is added
+        move_counter: bool,
+    },
+}
+
+impl InputEncoding {
+    /// creates the encoder for this encoding
+    pub fn encoder(&self) -> Box<dyn InputEncoder> {
+        match *self {
+            Self::OneHot => Box::new(OneHotEncoder),
+            Self::History {
+                history_len,
+                side_to_move,
+                move_counter,
+            } => Box::new(HistoryEncoder {
+                history_len,
+                side_to_move,
+                move_counter,
+            }),
+        }
+    }
+}
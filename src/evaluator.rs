@@ -0,0 +1,249 @@
+use super::game::{AbaloneGame, Board, BOARD_SIZE};
+
+/// index of the centre field in both board dimensions
+const CENTRE: i32 = 5;
+/// distance of the outermost ring of fields from the centre
+const EDGE_DISTANCE: i32 = 4;
+
+/// position that is evaluated during the search
+///
+/// all boards are given from the point of view of the side to move, so the
+/// marbles of that side are white (1) and the opponent's marbles are black (2).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    /// current board
+    pub board: Board,
+    /// previous boards, the most recent first. Contains at most as many boards as the
+    /// evaluator requests with `history_len`, fewer at the start of a game
+    pub history: Vec<Board>,
+    /// true if black is to move, the boards are rotated then
+    pub black_tomove: bool,
+    /// number of moves made since the start of the game
+    pub num_moves: usize,
+}
+
+impl Position {
+    /// returns a position without history for a board with white to move
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - board from the point of view of the side to move
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::evaluator::Position;
+    /// let position = Position::from_board(rustai_abalone::game::BELGIAN_DAISY);
+    /// assert!(position.history.is_empty());
+    /// ```
+    pub fn from_board(board: Board) -> Self {
+        Self {
+            board,
+            history: Vec::new(),
+            black_tomove: false,
+            num_moves: 0,
+        }
+    }
+
+    /// returns the current position of a game
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - game in the position
+    /// * `previous` - previous boards as they are on the board (not rotated), the most recent first
+    /// * `history_len` - maximum number of previous boards to keep
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::evaluator::Position;
+    /// use rustai_abalone::game::{AbaloneGame, BELGIAN_DAISY};
+    /// let mut abalone = AbaloneGame::new(BELGIAN_DAISY);
+    /// abalone.calc_reasonalbe_moves();
+    /// abalone.update_by_id(0);
+    /// let position = Position::from_game(&abalone, &[BELGIAN_DAISY], 2);
+    /// assert_eq!(position.history.len(), 1);
+    /// assert_eq!(position.num_moves, 1);
+    /// ```
+    pub fn from_game(game: &AbaloneGame, previous: &[Board], history_len: usize) -> Self {
+        let black_tomove = game.get_black_tomove();
+        let history = previous
+            .iter()
+            .take(history_len)
+            .map(|board| if black_tomove { AbaloneGame::rotate_board(*board) } else { *board })
+            .collect();
+        Self {
+            board: game.get_rotated_state(),
+            history,
+            black_tomove,
+            num_moves: game.get_num_moves(),
+        }
+    }
+}
+
+/// evaluates positions for the MCTS of `MagisterLudi`
+///
+/// An evaluator is shared between the MCTS threads of an agent. Every
+/// `model::PolicyValueModel` is an evaluator as well.
+pub trait Evaluator: Send + Sync {
+    /// returns the probabilities of the given moves
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - position from the point of view of the side to move
+    /// * `move_ids` - IDs of the reasonable moves in that position, see `marble_moves::MOVES_IDX`
+    ///
+    /// # Returns
+    ///
+    /// * `priors` - probability for every move, ordered like `move_ids` and summing up to 1
+    fn priors(&self, position: &Position, move_ids: &[usize]) -> Vec<f32>;
+
+    /// returns the evaluation of a position
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - position from the point of view of the side to move
+    ///
+    /// # Returns
+    ///
+    /// * `value` - between -1 and 1, 1 means a win for the side to move
+    fn value(&self, position: &Position) -> f32;
+
+    /// returns the number of previous boards the evaluator needs, the search only
+    /// collects them if this is not 0
+    fn history_len(&self) -> usize {
+        0
+    }
+}
+
+/// weights of the features `HeuristicEvaluator` combines
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HeuristicWeights {
+    /// weight of the difference in the number of marbles
+    pub marbles: f32,
+    /// weight of the difference in the average distance of the marbles from the centre
+    pub centre: f32,
+    /// weight of the difference in the average number of neighbouring marbles of the same colour
+    pub cohesion: f32,
+    /// weight of the difference in the number of marbles on the outermost ring
+    pub edge_danger: f32,
+}
+
+impl Default for HeuristicWeights {
+    fn default() -> Self {
+        Self {
+            marbles: 0.5,
+            centre: 0.3,
+            cohesion: 0.1,
+            edge_danger: 0.05,
+        }
+    }
+}
+
+/// evaluator that works without a neural network
+///
+/// the value is calculated from hand-made features of the board and every
+/// reasonable move is equally likely. It is much weaker than the trained model,
+/// but lets the agent run on machines without TensorFlow or the model files.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct HeuristicEvaluator {
+    /// weights of the features
+    pub weights: HeuristicWeights,
+}
+
+impl HeuristicEvaluator {
+    /// creates a heuristic evaluator with the given feature weights
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - weights of the features
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::evaluator::{HeuristicEvaluator, HeuristicWeights};
+    /// let evaluator = HeuristicEvaluator::new(HeuristicWeights { cohesion: 0.0, ..HeuristicWeights::default() });
+    /// ```
+    pub fn new(weights: HeuristicWeights) -> Self {
+        Self { weights }
+    }
+
+    // hexagonal distance of a field from the centre of the board
+    fn centre_distance(x: usize, y: usize) -> i32 {
+        let dx = x as i32 - CENTRE;
+        let dy = y as i32 - CENTRE;
+        (dx.abs() + dy.abs() + (dx + dy).abs()) / 2
+    }
+
+    // number of neighbouring fields occupied by a marble of the same colour
+    fn same_neighbours(board: &Board, x: usize, y: usize) -> i32 {
+        let colour = board[x][y];
+        AbaloneGame::get_game_moves()
+            .iter()
+            .filter(|marble_move| {
+                // fields of the board are surrounded by off-board fields, so neighbours stay in range
+                let nx = (x as i8 + marble_move.dx) as usize;
+                let ny = (y as i8 + marble_move.dy) as usize;
+                board[nx][ny] == colour
+            })
+            .count() as i32
+    }
+}
+
+impl Evaluator for HeuristicEvaluator {
+    /// every move is equally likely
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::evaluator::{Evaluator, HeuristicEvaluator, Position};
+    /// let position = Position::from_board(rustai_abalone::game::BELGIAN_DAISY);
+    /// let priors = HeuristicEvaluator::default().priors(&position, &[3, 7]);
+    /// assert_eq!(priors, vec![0.5, 0.5]);
+    /// ```
+    fn priors(&self, _position: &Position, move_ids: &[usize]) -> Vec<f32> {
+        vec![1.0 / move_ids.len() as f32; move_ids.len()]
+    }
+
+    /// combines marble count, centre distance, cohesion and marbles at the edge
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::evaluator::{Evaluator, HeuristicEvaluator, Position};
+    /// // the starting position is symmetric
+    /// let value = HeuristicEvaluator::default().value(&Position::from_board(rustai_abalone::game::BELGIAN_DAISY));
+    /// assert!(value.abs() < 1e-6);
+    /// ```
+    fn value(&self, position: &Position) -> f32 {
+        let board = &position.board;
+        // sums for white (index 0) and black (index 1)
+        let mut marbles = [0i32; 2];
+        let mut distance = [0i32; 2];
+        let mut neighbours = [0i32; 2];
+        let mut edge = [0i32; 2];
+        for x in 1..BOARD_SIZE - 1 {
+            for y in 1..BOARD_SIZE - 1 {
+                let side = match board[x][y] {
+                    1 => 0,
+                    2 => 1,
+                    _ => continue,
+                };
+                let centre_distance = Self::centre_distance(x, y);
+                marbles[side] += 1;
+                distance[side] += centre_distance;
+                neighbours[side] += Self::same_neighbours(board, x, y);
+                if centre_distance == EDGE_DISTANCE {
+                    edge[side] += 1;
+                }
+            }
+        }
+        let average = |sum: i32, count: i32| if count == 0 { 0.0 } else { sum as f32 / count as f32 };
+        let weights = &self.weights;
+        let score = weights.marbles * (marbles[0] - marbles[1]) as f32
+            + weights.centre * (average(distance[1], marbles[1]) - average(distance[0], marbles[0]))
+            + weights.cohesion * (average(neighbours[0], marbles[0]) - average(neighbours[1], marbles[1]))
+            + weights.edge_danger * (edge[1] - edge[0]) as f32;
+        score.tanh()
+    }
+}
@@ -429,6 +429,7 @@ pub struct AbaloneGame {
     save_history: bool,
     state_memory: std::collections::HashMap<Board, u8>,
     turn_number: usize,
+    num_moves: usize,
     noloss_turns: usize,
     noloss_moves: usize,
     white_loss: u8,
@@ -506,6 +507,7 @@ impl AbaloneGame {
             save_history: true,
             state_memory: std::collections::HashMap::with_capacity(150),
             turn_number: 1,
+            num_moves: 0,
             noloss_turns: 0,
             noloss_moves: 0,
             white_loss: Self::MARBLES_MAX - Self::count_marbles(board, Self::WHITE),
@@ -513,10 +515,41 @@ impl AbaloneGame {
             result: 10,
             game_ended: false,
         };
+        new_game.state_history.push(board);
         new_game.check_game_ended();
         new_game
     }
 
+    /// returns a new Abalone game instance for a position with the given side to move
+    ///
+    /// the states before the position are unknown, so the history only contains the position
+    /// itself and the count of moves without a loss of a marble starts at 0
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - the position as it is on the board (not rotated)
+    /// * `black_tomove` - if true, black has to make the next move, otherwise white
+    /// * `num_moves` - number of moves made since the start of the game, see `get_num_moves`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::game::{AbaloneGame, BELGIAN_DAISY};
+    /// let abalone = AbaloneGame::from_position(BELGIAN_DAISY, false, 1);
+    /// assert!(!abalone.get_black_tomove());
+    /// assert_eq!(abalone.get_num_moves(), 1);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the given board is not a valid one
+    pub fn from_position(board: Board, black_tomove: bool, num_moves: usize) -> Self {
+        let mut new_game = Self::new(board);
+        new_game.black_tomove = black_tomove;
+        new_game.num_moves = num_moves;
+        new_game
+    }
+
     /// creates a copy the current AbaloneGame instance for MCTS
     ///
     /// the copy will contain the all necessary state information,
@@ -539,6 +572,7 @@ impl AbaloneGame {
             save_history: false,
             state_memory: self.state_memory.clone(),
             turn_number: self.turn_number,
+            num_moves: self.num_moves,
             noloss_turns: self.noloss_turns,
             noloss_moves: self.noloss_moves,
             white_loss: self.white_loss,
@@ -744,6 +778,21 @@ impl AbaloneGame {
         self.black_tomove
     }
 
+    /// standard getter, returns the number of moves made since the game was created
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rustai_abalone::game::{AbaloneGame, BELGIAN_DAISY};
+    /// let mut abalone = AbaloneGame::new(BELGIAN_DAISY);
+    /// abalone.calc_reasonalbe_moves();
+    /// abalone.update_by_id(0);
+    /// assert_eq!(abalone.get_num_moves(), 1);
+    /// ```
+    pub fn get_num_moves(&self) -> usize {
+        self.num_moves
+    }
+
     /// standard getter for marbles lost by black and white
     /// 
     /// # Examples
@@ -859,6 +908,24 @@ impl AbaloneGame {
         self.board
     }
 
+    /// returns all states of the game, the starting position first and the current state last
+    ///
+    /// the states are given as they are on the board (not rotated). Copies created by
+    /// `mcts_copy` do not store the history, so they return an empty slice.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use rustai_abalone::game::{AbaloneGame, BELGIAN_DAISY};
+    /// let mut abalone = AbaloneGame::new(BELGIAN_DAISY);
+    /// abalone.calc_reasonalbe_moves();
+    /// abalone.update_by_id(0);
+    /// assert_eq!(abalone.get_state_history().len(), 2);
+    /// assert_eq!(abalone.get_state_history()[0], BELGIAN_DAISY);
+    /// ```
+    pub fn get_state_history(&self) -> &[Board] {
+        &self.state_history
+    }
+
     /// returns the current board state such that it is seen from white's perspective
     ///
     /// this function is meant to return state such that it is viewed from white's
@@ -1221,6 +1288,9 @@ impl AbaloneGame {
                                 } else {
                                     break;
                                 }
+                                // the key consists of all moved marbles, including the first one
+                                // of the second side whose single marble move is skipped below
+                                moved_pos = format!("{}{}{}", moved_pos, mar_pos.x, mar_pos.y);
                                 // avoids pushing the same single marble move twice
                                 if s > 0 && b == 0 {
                                     continue;
@@ -1228,23 +1298,12 @@ impl AbaloneGame {
                                 self.next_positions.push(new_board);
                                 next_moveids.push(
                                     match marble_moves::MOVES_IDX.get(
-                                        // the key consists of the already moved marbles combined with the current one
-                                        format!(
-                                            "{}{}{}{}{}",
-                                            moved_pos,
-                                            mar_pos.x,
-                                            mar_pos.y,
-                                            marb_move.dx,
-                                            marb_move.dy
-                                        )
-                                        .as_str(),
+                                        format!("{}{}{}", moved_pos, marb_move.dx, marb_move.dy).as_str(),
                                     ) {
                                         Some(idx) => *idx,
                                         None => panic!("A non existent move ID was created"),
                                     },
                                 );
-                                // add current marble to the alreadyd moved marble
-                                moved_pos = format!("{}{}{}", moved_pos, mar_pos.x, mar_pos.y);
                             } else {
                                 break;
                             }
@@ -1354,6 +1413,7 @@ impl AbaloneGame {
 
         self.board = new_board;
         self.black_tomove = !self.black_tomove;
+        self.num_moves += 1;
 
         let mut noloss: bool = true;
         let white_newloss = Self::MARBLES_MAX - Self::count_marbles(new_board, Self::WHITE);
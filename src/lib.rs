@@ -14,29 +14,198 @@
 //! the results of these simulations will be averaged to predict the move with the highest
 //! winning chance.
 
+pub mod arena;
+pub mod batch;
+pub mod book;
+pub mod cache;
+pub mod config;
+pub mod encoder;
+pub mod evaluator;
 pub mod game;
 pub mod marble_moves;
+pub mod model;
+#[cfg(feature = "npz")]
+pub mod npz;
 pub mod player;
+pub mod players;
+pub mod ratings;
+pub mod registry;
+pub mod scheduler;
+pub mod selfplay;
+pub mod store;
+pub mod training;
 pub mod util;
 
 #[cfg(test)]
 mod tests {
-    use util::{download_model, check_model_present};
+    #[cfg(feature = "download")]
+    use util::{download_model, download_model_with, check_model_present, DownloadError, DownloadOptions};
+    #[cfg(feature = "download")]
+    use sha2::{Digest, Sha256};
+    #[cfg(feature = "download")]
+    use std::io::{BufRead, BufReader, Write};
+    #[cfg(feature = "download")]
+    use std::net::TcpListener;
+    use arena::{Arena, ArenaOptions, ArenaResult, Sprt, SprtDecision};
+    use batch::BatchedModel;
+    use book::{canonical_position, BookBuilder, BookOptions, OpeningBook};
+    use cache::DistributionCache;
+    use config::{AgentConfig, Difficulty};
+    use encoder::{HistoryEncoder, InputEncoder, OneHotEncoder};
+    use evaluator::{Evaluator, HeuristicEvaluator, Position};
+    #[cfg(feature = "download")]
     use std::path::Path;
-    use game::{AbaloneGame, BELGIAN_DAISY};
-    use player::MagisterLudi;
+    use std::sync::Arc;
+    use game::{AbaloneGame, BELGIAN_DAISY, GERMAN_DAISY};
+    use model::{PolicyValueModel, Prediction, NUM_MOVES};
+    use player::{AgentAction, MagisterLudi};
+    use players::{GreedyPlayer, HumanPlayer, Match, Player, RandomPlayer, Termination};
+    use ratings::{GameResult, RatingError, RatingOptions, RatingTable};
+    use registry::ModelRegistry;
+    use scheduler::{SchedulerOptions, SelfPlayScheduler};
+    use selfplay::SelfPlayRunner;
+    use store::{ModelManifest, ModelStore, StoreError};
+    use training::{Symmetry, TrainingSample};
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use rand::Rng;
 
     use super::*;
 
     #[test]
+    #[cfg(feature = "download")]
     fn test_download() {
         let dl_folder = Path::new(".").join("test_download");
-        let path_to_model = download_model(dl_folder.to_str().unwrap());
+        let path_to_model = download_model(dl_folder.to_str().unwrap()).unwrap();
         let check = check_model_present(&path_to_model).is_some();
         assert!(check);
     }
 
+    // zip archive with placeholders for the model files followed by the extra entries
+    #[cfg(feature = "download")]
+    fn model_archive(extra: &[&str]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        for file in [
+            "saved_model.pb",
+            "keras_metadata.pb",
+            "fingerprint.pb",
+            "variables/variables.index",
+            "variables/variables.data-00000-of-00001",
+        ] {
+            zip.start_file(format!("magister_zero_unwrap_save/{file}"), options).unwrap();
+            zip.write_all(file.as_bytes()).unwrap();
+        }
+        for name in extra {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(b"#!/bin/sh").unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    // serves the archive to the given number of requests like a mirror that supports range requests
+    #[cfg(feature = "download")]
+    fn serve_archive(archive: Vec<u8>, requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/magister_zero.zip", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut start = 0;
+                let mut line = String::new();
+                // the headers end with an empty line
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if let Some(range) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        start = range.trim().trim_end_matches('-').parse().unwrap();
+                    }
+                    line.clear();
+                }
+                let status = if start > 0 { "206 Partial Content" } else { "200 OK" };
+                let body = &archive[start..];
+                write!(stream, "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    #[cfg(feature = "download")]
+    fn test_download_mirror() {
+        let archive = model_archive(&[]);
+        let checksum: String = Sha256::digest(&archive).iter().map(|byte| format!("{byte:02x}")).collect();
+        let dl_folder = std::env::temp_dir().join(format!("rustai_abalone_mirror_{}", std::process::id()));
+        let output_dir = dl_folder.to_str().unwrap();
+        // a corrupted archive is rejected and leaves no model behind
+        let options = DownloadOptions {
+            url: serve_archive(archive.clone(), 1),
+            sha256: Some("0".repeat(64)),
+            ..DownloadOptions::default()
+        };
+        let result = download_model_with(output_dir, &options);
+        assert!(matches!(result, Err(DownloadError::Checksum { .. })));
+        assert!(check_model_present(output_dir).is_none());
+        // a partial archive of another version is continued, but rejected and removed
+        let partial = dl_folder.join("magister_zero.zip.part");
+        std::fs::write(&partial, b"old version").unwrap();
+        let options = DownloadOptions {
+            url: serve_archive(archive.clone(), 1),
+            sha256: Some(checksum.clone()),
+            ..DownloadOptions::default()
+        };
+        let result = download_model_with(output_dir, &options);
+        assert!(matches!(result, Err(DownloadError::Checksum { .. })));
+        assert!(!partial.exists() && check_model_present(output_dir).is_none());
+        // an interrupted download is resumed
+        std::fs::write(&partial, &archive[..archive.len() / 2]).unwrap();
+        let options = DownloadOptions {
+            url: serve_archive(archive.clone(), 1),
+            sha256: Some(checksum),
+            ..DownloadOptions::default()
+        };
+        let model_path = download_model_with(output_dir, &options).unwrap();
+        assert_eq!(check_model_present(output_dir), Some(model_path.clone()));
+        // without a checksum a partial archive is downloaded again instead of resumed
+        std::fs::write(&partial, b"old version").unwrap();
+        let unverified = DownloadOptions {
+            url: serve_archive(archive, 1),
+            sha256: None,
+            ..DownloadOptions::default()
+        };
+        assert_eq!(download_model_with(output_dir, &unverified).unwrap(), model_path);
+        assert!(!partial.exists());
+        let offline = DownloadOptions { offline: true, ..options };
+        assert!(matches!(download_model_with(output_dir, &offline), Err(DownloadError::Offline)));
+        std::fs::remove_dir_all(&dl_folder).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "download")]
+    fn test_download_unsafe_archive() {
+        let dl_folder = std::env::temp_dir().join(format!("rustai_abalone_unsafe_{}", std::process::id()));
+        let output_dir = dl_folder.join("models");
+        let download = |archive: Vec<u8>, max_extracted_size: u64| {
+            let options = DownloadOptions {
+                sha256: Some(Sha256::digest(&archive).iter().map(|byte| format!("{byte:02x}")).collect()),
+                url: serve_archive(archive, 1),
+                max_extracted_size,
+                ..DownloadOptions::default()
+            };
+            download_model_with(output_dir.to_str().unwrap(), &options)
+        };
+        let result = download(model_archive(&["../evil.sh"]), u64::MAX);
+        assert!(matches!(result, Err(DownloadError::UnsafeEntry(_))));
+        assert!(!output_dir.join("evil.sh").exists() && !dl_folder.join("evil.sh").exists());
+        let result = download(model_archive(&["/tmp/evil.sh"]), u64::MAX);
+        assert!(matches!(result, Err(DownloadError::UnsafeEntry(_))));
+        let result = download(model_archive(&["magister_zero_unwrap_save/run.sh"]), u64::MAX);
+        assert!(matches!(result, Err(DownloadError::UnexpectedEntry(_))));
+        let result = download(model_archive(&[]), 20);
+        assert!(matches!(result, Err(DownloadError::TooLarge { limit: 20, .. })));
+        assert!(check_model_present(output_dir.to_str().unwrap()).is_none());
+        std::fs::remove_dir_all(&dl_folder).unwrap();
+    }
+
     #[test]
     fn test_abalone_game() {
         // change board to something with all possibilities
@@ -83,6 +252,42 @@ mod tests {
         abalone.calc_reasonalbe_moves();
         let next_pos = abalone.get_next_position(0);
         abalone.update_state(next_pos);
+
+        // the second side of a broad side move skips the single marble move of its first marble,
+        // the move is still identified by both marbles, '545510' has the ID 1310
+        let mut pov_board = BELGIAN_DAISY.map(|row| row.map(|field| if field == 3 { 3 } else { 0 }));
+        pov_board[5][4] = 1;
+        pov_board[5][5] = 1;
+        pov_board[1][5] = 2;
+        let mut abalone = AbaloneGame::new(AbaloneGame::rotate_board(pov_board));
+        let (state, move_ids) = abalone.calc_reasonalbe_moves();
+        assert_eq!(state, pov_board);
+        assert_eq!(marble_moves::MOVES_IDX["545510"], 1310);
+        let broad_side = move_ids.iter().position(|&move_id| move_id == 1310).unwrap();
+        let mut expected = pov_board;
+        expected[5][4] = 0;
+        expected[5][5] = 0;
+        expected[6][4] = 1;
+        expected[6][5] = 1;
+        assert_eq!(abalone.get_next_position(broad_side), expected);
+        // the single marble moves '5410' and '5510' are generated exactly once
+        for single in [174, 180] {
+            assert_eq!(move_ids.iter().filter(|&&move_id| move_id == single).count(), 1);
+        }
+
+        // every move ID of a position leads to exactly one follow-up position, so the policy
+        // indexed by `marble_moves::MOVES_IDX` never mixes up two moves
+        let mut rng = rand::thread_rng();
+        let mut abalone = AbaloneGame::new(BELGIAN_DAISY);
+        while !abalone.get_game_ended() && abalone.get_num_moves() < 200 {
+            let (_, move_ids) = abalone.calc_reasonalbe_moves();
+            let mut positions = std::collections::HashMap::new();
+            for (idx, move_id) in move_ids.iter().enumerate() {
+                let next_position = abalone.get_next_position(idx);
+                assert_eq!(*positions.entry(*move_id).or_insert(next_position), next_position);
+            }
+            abalone.update_by_id(rng.gen_range(0..move_ids.len()));
+        }
     }
 
     #[test]
@@ -119,10 +324,775 @@ mod tests {
     }
 
     #[test]
+    fn test_distribution_cache() {
+        // a cap of one kilobyte allows only a single entry per generation and shard
+        let cache = DistributionCache::new(1024);
+        let mut abalone = AbaloneGame::new(BELGIAN_DAISY);
+        let mut boards = Vec::new();
+        for _ in 0..100 {
+            let (state, move_ids) = abalone.calc_reasonalbe_moves();
+            boards.push(state);
+            cache.insert(state, vec![1.0; move_ids.len()]);
+            abalone.update_by_id(0);
+        }
+        let stats = cache.stats();
+        assert!(stats.entries <= 32);
+        assert!(stats.evictions > 0);
+        // the most recent position is always kept
+        let last = *boards.last().unwrap();
+        assert!(cache.get_with(&last, |_| ()).is_some());
+        assert_eq!(cache.stats().hits, 1);
+        cache.clear();
+        assert!(cache.get_with(&last, |_| ()).is_none());
+        assert_eq!(cache.stats().misses, 1);
+
+        // only the simulations look up distributions, the root priors are peeked
+        let config = AgentConfig::builder().mcts_num(20).mcts_parallel(0).mcts_depth(1).build().unwrap();
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        magi_ludi.own_move(true);
+        let stats = magi_ludi.cache_stats();
+        assert_eq!(stats.hits + stats.misses, 20);
+        magi_ludi.stop_execution();
+    }
+
+    #[test]
+    fn test_difficulty_levels() {
+        let mut last_num = 0;
+        let mut last_blunder = 1.0;
+        for level in Difficulty::ALL {
+            let config = level.config();
+            assert!(config.validate().is_ok());
+            // stronger levels search more and blunder less
+            assert!(config.mcts_num > last_num);
+            assert!(config.move_selection.blunder_probability <= last_blunder);
+            last_num = config.mcts_num;
+            last_blunder = config.move_selection.blunder_probability;
+            assert_eq!(level.to_string().parse::<Difficulty>().unwrap(), level);
+        }
+        assert!("grandmaster".parse::<Difficulty>().is_err());
+    }
+
+    #[test]
+    fn test_heuristic_evaluator() {
+        let evaluator = HeuristicEvaluator::default();
+        let mut board = BELGIAN_DAISY;
+        // remove a black marble, so white is ahead
+        board[1][8] = 0;
+        let position = Position::from_board(board);
+        let value = evaluator.value(&position);
+        assert!(value > 0.0 && value < 1.0);
+        assert!((evaluator.value(&Position::from_board(AbaloneGame::rotate_board(board))) + value).abs() < 1e-6);
+        let priors = evaluator.priors(&position, &[0, 1, 2, 3]);
+        assert_eq!(priors, vec![0.25; 4]);
+    }
+
+    #[test]
+    fn test_magister_ludi_heuristic() {
+        let config = AgentConfig::builder().mcts_num(30).mcts_parallel(2).mcts_depth(4).build().unwrap();
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        for _ in 0..4 {
+            let chosen_move = magi_ludi.own_move(true).board().unwrap();
+            assert!(AbaloneGame::validate_board(chosen_move));
+        }
+        magi_ludi.start_new_game(BELGIAN_DAISY);
+        magi_ludi.stop_execution();
+    }
+
+    #[test]
+    fn test_magister_ludi_threads() {
+        let config = AgentConfig::builder().mcts_num(40).mcts_parallel(4).mcts_depth(3).build().unwrap();
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        // the idle threads pick up the leafs of every move and each result is collected exactly once
+        for _ in 0..3 {
+            assert!(magi_ludi.own_move(true).board().is_some());
+            let report = magi_ludi.last_search_report().unwrap();
+            assert_eq!(report.children.iter().map(|child| child.visits).sum::<usize>(), 40);
+            assert!(magi_ludi.check_threads_all_active());
+        }
+        // the stop signal wakes the waiting threads, so they can be joined
+        magi_ludi.stop_execution();
+        assert!(!magi_ludi.check_threads_all_active());
+        magi_ludi.start_new_game(BELGIAN_DAISY);
+        assert!(magi_ludi.check_threads_all_active());
+        assert!(magi_ludi.own_move(true).board().is_some());
+        magi_ludi.stop_execution();
+    }
+
+    #[test]
+    fn test_magister_ludi_analyse() {
+        // evaluator remembering the smallest move number it was asked about
+        struct MoveCountEvaluator(AtomicUsize);
+
+        impl Evaluator for MoveCountEvaluator {
+            fn priors(&self, position: &Position, move_ids: &[usize]) -> Vec<f32> {
+                self.0.fetch_min(position.num_moves, Ordering::SeqCst);
+                HeuristicEvaluator::default().priors(position, move_ids)
+            }
+
+            fn value(&self, position: &Position) -> f32 {
+                HeuristicEvaluator::default().value(position)
+            }
+        }
+
+        let config = AgentConfig::builder()
+            .mcts_num(30)
+            .mcts_parallel(2)
+            .mcts_depth(3)
+            .move_selection(player::MoveSelection::self_play())
+            .build()
+            .unwrap();
+        let evaluator = Arc::new(MoveCountEvaluator(AtomicUsize::new(usize::MAX)));
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, evaluator.clone());
+        magi_ludi.own_move(true);
+        let state = magi_ludi.game().get_state();
+        let history = magi_ludi.game().get_state_history().to_vec();
+        evaluator.0.store(usize::MAX, Ordering::SeqCst);
+        let report = magi_ludi.analyse(GERMAN_DAISY, false, 7, player::SearchBudget::new(40, 3));
+        assert_eq!(evaluator.0.load(Ordering::SeqCst), 7);
+        assert_eq!(report.children.len(), 3);
+        assert!(report.children.windows(2).all(|pair| pair[0].mean_value >= pair[1].mean_value));
+        // the simulations are assigned without the noise of the self-play selection
+        let visits = |report: &player::SearchReport| {
+            let mut visits: Vec<(usize, usize)> = report.children.iter().map(|child| (child.move_id, child.visits)).collect();
+            visits.sort();
+            visits
+        };
+        let all_moves = player::SearchBudget::new(40, usize::MAX);
+        let first = magi_ludi.analyse(GERMAN_DAISY, false, 7, all_moves);
+        assert_eq!(visits(&magi_ludi.analyse(GERMAN_DAISY, false, 7, all_moves)), visits(&first));
+        // the candidates are the follow-up positions of the analysed position with white to move
+        let mut analysed = AbaloneGame::from_position(GERMAN_DAISY, false, 7);
+        assert!(!analysed.get_black_tomove());
+        let (_, move_ids) = analysed.calc_reasonalbe_moves();
+        for child in &report.children {
+            let idx = move_ids.iter().position(|id| *id == child.move_id).unwrap();
+            assert_eq!(analysed.get_next_position(idx), child.board);
+        }
+        // the game of the agent and its history are not changed
+        assert_eq!(magi_ludi.game().get_state(), state);
+        assert_eq!(magi_ludi.game().get_state_history(), history.as_slice());
+        assert_eq!(magi_ludi.game().get_num_moves(), 1);
+        assert!(!magi_ludi.game().get_black_tomove());
+        magi_ludi.stop_execution();
+    }
+
+    #[test]
+    #[should_panic(expected = "multi_pv")]
+    fn test_magister_ludi_analyse_budget() {
+        let config = AgentConfig::builder().mcts_num(10).mcts_parallel(0).mcts_depth(3).build().unwrap();
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        magi_ludi.analyse(BELGIAN_DAISY, true, 0, player::SearchBudget::new(10, 0));
+    }
+
+    #[test]
+    fn test_move_selection() {
+        // own and external moves are counted, copies for the search keep the count
+        let mut abalone = AbaloneGame::new(BELGIAN_DAISY);
+        assert_eq!(abalone.get_num_moves(), 0);
+        for _ in 0..3 {
+            abalone.calc_reasonalbe_moves();
+            abalone.update_by_id(0);
+        }
+        assert_eq!(abalone.get_num_moves(), 3);
+        assert_eq!(abalone.mcts_copy().get_num_moves(), 3);
+
+        // greedy mode spreads the simulations by the priors, which are uniform for the heuristic
+        let (_, move_ids) = AbaloneGame::new(BELGIAN_DAISY).calc_reasonalbe_moves();
+        let config = AgentConfig::builder()
+            .mcts_num(3 * move_ids.len())
+            .mcts_parallel(0)
+            .mcts_depth(2)
+            .move_selection(player::MoveSelection::greedy())
+            .build()
+            .unwrap();
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        let state = magi_ludi.own_move(true).board().unwrap();
+        let report = magi_ludi.last_search_report().unwrap();
+        assert_eq!(report.children.len(), move_ids.len());
+        assert!(report.children.iter().all(|child| child.visits == 3));
+        // without temperature the move with the best mean value is played
+        assert_eq!(state, report.best_child().unwrap().board);
+        assert_eq!(magi_ludi.game().get_num_moves(), 1);
+
+        // the schedule switches the temperature at the given move numbers
+        let selection = player::MoveSelection { temperature_schedule: vec![(0, 2.0), (2, 0.0)], ..player::MoveSelection::self_play() };
+        assert_eq!((selection.temperature(1), selection.temperature(2)), (2.0, 0.0));
+        magi_ludi.set_move_selection(selection.clone());
+        assert_eq!(magi_ludi.move_selection(), &selection);
+        assert!(magi_ludi.own_move(true).board().is_some());
+        assert_eq!(magi_ludi.game().get_num_moves(), 2);
+        magi_ludi.stop_execution();
+    }
+
+    // model preferring the first move ID of every position
+    struct FirstMoveModel;
+
+    impl PolicyValueModel for FirstMoveModel {
+        fn predict(&self, positions: &[Position]) -> Vec<Prediction> {
+            positions
+                .iter()
+                .map(|_| {
+                    let mut policy_logits = vec![0.0; NUM_MOVES];
+                    policy_logits[0] = 5.0;
+                    Prediction { policy_logits, value: 0.0 }
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_policy_value_model() {
+        let priors = FirstMoveModel.priors(&Position::from_board(BELGIAN_DAISY), &[0, 1]);
+        assert!(priors[0] > priors[1]);
+        assert!((priors.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        let config = AgentConfig::builder().mcts_num(20).mcts_parallel(2).mcts_depth(3).build().unwrap();
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(FirstMoveModel));
+        let chosen_move = magi_ludi.own_move(true).board().unwrap();
+        assert!(AbaloneGame::validate_board(chosen_move));
+        magi_ludi.stop_execution();
+    }
+
+    #[test]
+    fn test_magister_ludi_set_evaluator() {
+        // evaluator preferring the later moves of every position
+        struct RankedEvaluator;
+
+        impl Evaluator for RankedEvaluator {
+            fn priors(&self, _position: &Position, move_ids: &[usize]) -> Vec<f32> {
+                let total = (move_ids.len() * (move_ids.len() + 1) / 2) as f32;
+                (1..=move_ids.len()).map(|rank| rank as f32 / total).collect()
+            }
+
+            fn value(&self, _position: &Position) -> f32 {
+                0.0
+            }
+        }
+
+        let config = AgentConfig::builder().mcts_num(20).mcts_parallel(2).mcts_depth(3).build().unwrap();
+        let heuristic: Arc<dyn Evaluator> = Arc::new(HeuristicEvaluator::default());
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, heuristic);
+        magi_ludi.own_move(true);
+        let priors: Vec<f32> = magi_ludi.last_search_report().unwrap().children.iter().map(|child| child.prior).collect();
+        assert!(priors.windows(2).all(|pair| pair[0] == pair[1]));
+        assert!(magi_ludi.cache_stats().entries > 0);
+        // the distributions of the heuristic are not reused for the new model
+        magi_ludi.set_evaluator(Arc::new(RankedEvaluator));
+        assert_eq!(magi_ludi.cache_stats().entries, 0);
+        assert!(magi_ludi.check_threads_all_active());
+        magi_ludi.start_new_game(BELGIAN_DAISY);
+        magi_ludi.own_move(true);
+        let priors: Vec<f32> = magi_ludi.last_search_report().unwrap().children.iter().map(|child| child.prior).collect();
+        assert!(priors.windows(2).any(|pair| pair[0] != pair[1]));
+        magi_ludi.stop_execution();
+    }
+
+    #[test]
+    fn test_batched_model() {
+        let model = Arc::new(BatchedModel::new(Arc::new(FirstMoveModel), 8, std::time::Duration::from_millis(20)));
+        let mut board = BELGIAN_DAISY;
+        board[1][8] = 0;
+        let positions = [Position::from_board(BELGIAN_DAISY), Position::from_board(board)];
+        // concurrent callers receive the predictions of their own positions in order
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let model = model.clone();
+                let positions = positions.clone();
+                std::thread::spawn(move || model.predict(&positions))
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), FirstMoveModel.predict(&positions));
+        }
+        let stats = model.stats();
+        assert_eq!(stats.positions, 8);
+        assert!(stats.batches < 8 && stats.average_batch_size() > 1.0);
+        // the batched model is an evaluator as well
+        let config = AgentConfig::builder().mcts_num(20).mcts_parallel(4).mcts_depth(3).build().unwrap();
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, model);
+        assert!(magi_ludi.own_move(true).board().is_some());
+        magi_ludi.stop_execution();
+    }
+
+    #[test]
+    fn test_self_play_scheduler() {
+        let config = AgentConfig::builder()
+            .mcts_num(10)
+            .mcts_depth(3)
+            .seed(5)
+            .move_selection(player::MoveSelection::self_play())
+            .build()
+            .unwrap();
+        // the workers share a pool of search threads or simulate their leafs themselves
+        for search_threads in [2, 0] {
+            let options = SchedulerOptions { workers: 2, search_threads, games: Some(3), max_moves: 6, ..Default::default() };
+            let scheduler = SelfPlayScheduler::new(config.clone(), Arc::new(HeuristicEvaluator::default()), options);
+            let mut game_ids = Vec::new();
+            let progress = scheduler.run(|game_id, game, progress| {
+                assert_eq!(game.records.len(), game.num_moves);
+                assert_eq!(progress.games, game_ids.len() + 1);
+                game_ids.push(game_id);
+            });
+            game_ids.sort_unstable();
+            assert_eq!(game_ids, vec![0, 1, 2]);
+            assert_eq!((progress.games, progress.positions, progress.draws), (3, 18, 3));
+            assert!(progress.games_per_hour() > 0.0);
+            // a stopped scheduler does not start new games
+            scheduler.stop_handle().stop();
+            assert_eq!(scheduler.run(|_, _, _| panic!("no game expected")).games, 0);
+        }
+        // after draining, the running games are finished but no new ones are started
+        let options = SchedulerOptions { workers: 2, search_threads: 2, games: None, max_moves: 6, ..Default::default() };
+        let scheduler = SelfPlayScheduler::new(config, Arc::new(HeuristicEvaluator::default()), options);
+        let stop_handle = scheduler.stop_handle();
+        let progress = scheduler.run(|_, game, _| {
+            assert_eq!(game.records.len(), 6);
+            stop_handle.drain();
+        });
+        assert!(progress.games >= 1);
+        assert!(!stop_handle.is_stopped());
+        assert_eq!(scheduler.run(|_, _, _| panic!("no game expected")).games, 0);
+    }
+
+    #[test]
+    fn test_search_pool() {
+        let pool = Arc::new(player::SearchPool::new(2, Some(3)));
+        assert_eq!(pool.threads(), 2);
+        let config = AgentConfig::builder().mcts_num(30).mcts_parallel(4).mcts_depth(3).seed(3).build().unwrap();
+        let evaluator = Arc::new(HeuristicEvaluator::default());
+        let mut agents: Vec<_> = (0..3)
+            .map(|_| MagisterLudi::with_search_pool(BELGIAN_DAISY, config.clone(), evaluator.clone(), pool.clone()))
+            .collect();
+        // the agents start no threads of their own, their leafs are simulated by the pool
+        assert!(agents.iter().all(|agent| agent.config().mcts_parallel == 0 && agent.check_threads_all_active()));
+        let mut handles = Vec::new();
+        for mut agent in agents.drain(..) {
+            handles.push(std::thread::spawn(move || {
+                for _ in 0..3 {
+                    assert!(agent.own_move(true).board().is_some());
+                    let report = agent.last_search_report().unwrap();
+                    assert_eq!(report.children.iter().map(|child| child.visits).sum::<usize>(), 30);
+                }
+                agent.set_config(AgentConfig { mcts_parallel: 2, ..agent.config().clone() }).unwrap();
+                assert_eq!(agent.config().mcts_parallel, 0);
+                agent.stop_execution();
+                // the pool keeps running for the other agents
+                assert!(agent.check_threads_all_active());
+            }));
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_arena() {
+        let create_agent = |mcts_num| {
+            let config = AgentConfig::builder().mcts_num(mcts_num).mcts_parallel(2).mcts_depth(3).build().unwrap();
+            MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()))
+        };
+        let options = ArenaOptions { games: 6, max_moves: 4, ..Default::default() };
+        let mut arena = Arena::new(create_agent(20), create_agent(10), options);
+        let mut games = Vec::new();
+        let result = arena.run(|game, result| {
+            assert_eq!(result.games(), game.index + 1);
+            games.push((game.layout, game.candidate_white));
+        });
+        // every layout is played with both colours
+        assert_eq!(games, vec![(0, false), (0, true), (1, false), (1, true), (2, false), (2, true)]);
+        assert_eq!(result, ArenaResult { wins: 0, draws: 6, losses: 0 });
+        let (mut candidate, mut baseline) = arena.into_agents();
+        candidate.stop_execution();
+        baseline.stop_execution();
+
+        let even = ArenaResult { wins: 40, draws: 20, losses: 40 };
+        assert_eq!(even.elo(), 0.0);
+        let (lower, upper) = even.elo_interval();
+        assert!((lower + upper).abs() < 1e-9 && upper > 0.0);
+        let sprt = Sprt::default();
+        assert!(sprt.bounds().0 < 0.0 && sprt.bounds().1 > 0.0);
+        assert_eq!(ArenaResult { wins: 3, draws: 0, losses: 0 }.sprt(&sprt).decision, SprtDecision::Continue);
+        assert_eq!(ArenaResult { wins: 100, draws: 800, losses: 400 }.sprt(&sprt).decision, SprtDecision::AcceptH0);
+        assert_eq!(ArenaResult { wins: 400, draws: 800, losses: 100 }.sprt(&sprt).decision, SprtDecision::AcceptH1);
+        // the arena stops as soon as the test decides
+        let options = ArenaOptions { games: 10, max_moves: 2, sprt: Some(Sprt { elo0: 100.0, elo1: 200.0, ..sprt }), ..Default::default() };
+        let mut arena = Arena::new(create_agent(10), create_agent(10), options);
+        assert!(arena.run(|_, _| ()).games() < 10);
+        let (mut candidate, mut baseline) = arena.into_agents();
+        candidate.stop_execution();
+        baseline.stop_execution();
+    }
+
+    #[test]
+    fn test_players() {
+        // plays random moves and offers a draw with every move, resigns or moves illegally if asked
+        struct ScriptedPlayer {
+            random: RandomPlayer,
+            resign: bool,
+            illegal: bool,
+        }
+        impl Player for ScriptedPlayer {
+            fn name(&self) -> String {
+                "scripted".to_string()
+            }
+            fn new_game(&mut self, board: game::Board) {
+                self.random.new_game(board);
+            }
+            fn opponent_move(&mut self, state: game::Board) {
+                self.random.opponent_move(state);
+            }
+            fn choose_move(&mut self) -> AgentAction {
+                match (self.resign, self.illegal) {
+                    (true, _) => AgentAction::Resign,
+                    (_, true) => AgentAction::Move(BELGIAN_DAISY),
+                    _ => AgentAction::MoveOfferingDraw(self.random.choose_move().board().unwrap()),
+                }
+            }
+            fn accept_draw(&mut self) -> bool {
+                true
+            }
+        }
+        let scripted = |resign, illegal| ScriptedPlayer { random: RandomPlayer::new(Some(3)), resign, illegal };
+
+        // every position of the history is reached by a legal move
+        let mut greedy_match = Match::new(GreedyPlayer::heuristic(Some(1)), RandomPlayer::new(Some(2)));
+        let result = greedy_match.play(BELGIAN_DAISY, 40);
+        assert_eq!(result.history.len(), result.num_moves + 1);
+        let mut replay = AbaloneGame::new(BELGIAN_DAISY);
+        for state in result.history.iter().skip(1) {
+            let black_tomove = replay.get_black_tomove();
+            let (_, move_ids) = replay.calc_reasonalbe_moves();
+            let next_pov = if black_tomove { AbaloneGame::rotate_board(*state) } else { *state };
+            assert!((0..move_ids.len()).any(|idx| replay.get_next_position(idx) == next_pov));
+            replay.update_state(next_pov);
+        }
+        assert_eq!(result.game_result().white, "GreedyPlayer");
+
+        // black moves first, so its resignation or illegal move is a win for white
+        let result = Match::new(RandomPlayer::new(None), scripted(true, false)).play(BELGIAN_DAISY, 40);
+        assert_eq!((result.result, result.termination, result.num_moves), (1, Termination::Resignation, 0));
+        let result = Match::new(RandomPlayer::new(None), scripted(false, true)).play(BELGIAN_DAISY, 40);
+        assert_eq!((result.result, result.termination), (1, Termination::IllegalMove));
+        let result = Match::new(scripted(false, false), scripted(false, false)).play(BELGIAN_DAISY, 40);
+        assert_eq!((result.result, result.termination, result.num_moves), (0, Termination::DrawAgreed, 1));
+
+        // the human resigns when the input ends
+        let human = HumanPlayer::new("alice", "72 73 62\n".as_bytes(), Vec::new());
+        let mut human_match = Match::new(RandomPlayer::new(None), human);
+        let result = human_match.play(BELGIAN_DAISY, 40);
+        assert_eq!((result.result, result.termination, result.num_moves), (1, Termination::Resignation, 2));
+        let (_, human) = human_match.into_players();
+        assert!(String::from_utf8_lossy(human.output()).contains("game over, white won"));
+
+        // the agent keeps its game in sync with the match
+        let config = AgentConfig::builder().mcts_num(10).mcts_parallel(0).mcts_depth(3).build().unwrap();
+        let agent = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        let mut agent_match = Match::new(agent, RandomPlayer::new(None));
+        let result = agent_match.play(BELGIAN_DAISY, 6);
+        assert_eq!((result.result, result.termination, result.num_moves), (0, Termination::MoveLimit, 6));
+        agent_match.into_players().0.stop_execution();
+    }
+
+    #[test]
+    fn test_opening_book() {
+        let mut rng = rand::thread_rng();
+        let mut game = AbaloneGame::new(GERMAN_DAISY);
+        for _ in 0..4 {
+            let (_, move_ids) = game.calc_reasonalbe_moves();
+            game.update_by_id(rng.gen_range(0..move_ids.len()));
+        }
+        let history = game.get_state_history().to_vec();
+        // the same game on a reflected and rotated board
+        let symmetry = Symmetry { rotation: 2, reflect: true };
+        let mirrored: Vec<_> = history.iter().map(|state| symmetry.transform_board(state)).collect();
+        let mut builder = BookBuilder::new(BookOptions::default());
+        assert_eq!(builder.add_game(&history), 4);
+        let single = builder.build();
+        assert_eq!(builder.add_game(&mirrored), 4);
+        let book = builder.build();
+        assert_eq!(book.len(), single.len());
+        assert!(book.positions.values().flatten().all(|book_move| book_move.weight == 2.0));
+
+        // the book moves of the mirrored game lead to the positions that were played
+        let mut replay = AbaloneGame::new(mirrored[0]);
+        for next_state in mirrored.iter().skip(1) {
+            let black_tomove = replay.get_black_tomove();
+            let book_moves = book.moves(&replay.get_state(), black_tomove);
+            let (_, move_ids) = replay.calc_reasonalbe_moves();
+            let idx = move_ids.iter().position(|id| *id == book_moves[0].move_id).unwrap();
+            let played = if black_tomove { AbaloneGame::rotate_board(*next_state) } else { *next_state };
+            assert_eq!(canonical_position(&replay.get_next_position(idx)).0, canonical_position(&played).0);
+            replay.update_state(played);
+        }
+        assert!(book.moves(&replay.get_state(), replay.get_black_tomove()).is_empty());
+
+        let path = std::env::temp_dir().join(format!("rustai_abalone_test_book_{}.json", std::process::id()));
+        book.save(&path).unwrap();
+        let loaded = OpeningBook::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, book);
+
+        // the agent plays the book moves without search and searches once the book ends
+        let config = AgentConfig::builder().mcts_num(10).mcts_parallel(0).mcts_depth(3).build().unwrap();
+        let mut agent = MagisterLudi::with_evaluator(GERMAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        agent.set_opening_book(Some(Arc::new(loaded)));
+        for next_state in history.iter().skip(1) {
+            let state = agent.own_move(false).board().unwrap();
+            assert!(agent.last_search_report().is_none());
+            let canonical = |state: &game::Board| canonical_position(&AbaloneGame::rotate_board(*state)).0;
+            assert_eq!(canonical(&state), canonical(next_state));
+        }
+        agent.own_move(false);
+        assert!(agent.last_search_report().is_some());
+
+        // a search tree stops after the maximum number of searched positions
+        let options = BookOptions { max_moves: 4, max_searches: 3, ..Default::default() };
+        let mut builder = BookBuilder::new(options);
+        assert_eq!(builder.add_search(&mut agent, BELGIAN_DAISY, player::SearchBudget::new(10, 3)), 3);
+        // moves below the minimum share are not followed
+        let options = BookOptions { max_moves: 4, min_move_share: 1.1, ..Default::default() };
+        let mut builder = BookBuilder::new(options);
+        assert_eq!(builder.add_search(&mut agent, BELGIAN_DAISY, player::SearchBudget::new(10, 3)), 1);
+        agent.stop_execution();
+    }
+
+    #[test]
+    fn test_ratings() {
+        let mut results = Vec::new();
+        // a beats b 3 of 4 games, b beats c 3 of 4 games, c never played a
+        for (winner, loser) in [("a", "b"), ("b", "c")] {
+            results.push(GameResult::new(winner, loser, 1));
+            results.push(GameResult::new(loser, winner, -1));
+            results.push(GameResult::new(winner, loser, 1));
+            results.push(GameResult::new(winner, loser, -1));
+        }
+        let table = RatingTable::from_results(&results, RatingOptions::default());
+        let names: Vec<&str> = table.ratings.iter().map(|rating| rating.player.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+        let (a, b, c) = (table.get("a").unwrap(), table.get("b").unwrap(), table.get("c").unwrap());
+        assert_eq!((a.games, b.games, c.games), (4, 8, 4));
+        assert_eq!((a.score, b.score, c.score), (0.75, 0.5, 0.25));
+        // symmetric results give symmetric ratings around the base rating
+        assert!((a.rating + c.rating - 2.0 * ratings::BASE_RATING).abs() < 1e-6);
+        assert!((a.uncertainty - c.uncertainty).abs() < 1e-6 && b.uncertainty < a.uncertainty);
+        // more games make the ratings more certain
+        let many: Vec<GameResult> = results.iter().cycle().take(results.len() * 10).cloned().collect();
+        assert!(RatingTable::from_results(&many, RatingOptions::default()).get("a").unwrap().uncertainty < a.uncertainty);
+        // players without losses keep a finite rating
+        let unbeaten = RatingTable::from_results(&[GameResult::new("a", "b", 1)], RatingOptions::default());
+        assert!(unbeaten.ratings[0].rating.is_finite());
+
+        let dir = std::env::temp_dir().join(format!("rustai_abalone_test_ratings_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let results_path = dir.join("results.txt");
+        GameResult::append(&results_path, &results[..4]).unwrap();
+        GameResult::append(&results_path, &results[4..]).unwrap();
+        assert_eq!(GameResult::read(&results_path).unwrap(), results);
+        let ratings_path = dir.join("ratings.json");
+        table.save(&ratings_path).unwrap();
+        let loaded = RatingTable::load(&ratings_path).unwrap();
+        assert_eq!(loaded.ratings.len(), table.ratings.len());
+        for (loaded, rating) in loaded.ratings.iter().zip(&table.ratings) {
+            assert_eq!((&loaded.player, loaded.games), (&rating.player, rating.games));
+            assert!((loaded.rating - rating.rating).abs() < 1e-9 && (loaded.uncertainty - rating.uncertainty).abs() < 1e-9);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(GameResult::parse("a b 2"), Err(RatingError::Parse { line: 1, .. })));
+        assert!(matches!(GameResult::parse("\na a 1"), Err(RatingError::Parse { line: 2, .. })));
+    }
+
+    #[test]
+    fn test_input_encoders() {
+        let mut abalone = AbaloneGame::new(BELGIAN_DAISY);
+        for _ in 0..4 {
+            abalone.calc_reasonalbe_moves();
+            abalone.update_by_id(0);
+        }
+        let previous: Vec<_> = abalone.get_state_history().iter().rev().skip(1).copied().collect();
+        let position = Position::from_game(&abalone, &previous, 2);
+        assert!(position.black_tomove);
+        assert_eq!(position.history.len(), 2);
+        let one_hot = OneHotEncoder.encode_batch(std::slice::from_ref(&position));
+        assert_eq!(one_hot.iter().sum::<f32>(), 121.0);
+
+        let encoder = HistoryEncoder { history_len: 4, side_to_move: true, move_counter: true };
+        let planes = encoder.planes();
+        assert_eq!(planes, 14);
+        let input = encoder.encode_batch(std::slice::from_ref(&position));
+        assert_eq!(input.len(), 11 * 11 * planes);
+        let plane_sum = |plane: usize| input.iter().skip(plane).step_by(planes).sum::<f32>();
+        // the first planes match the one-hot encoding
+        for plane in 0..4 {
+            assert_eq!(plane_sum(plane), one_hot.iter().skip(plane).step_by(4).sum::<f32>());
+        }
+        // own and opposing marbles of the two previous boards, the others are missing
+        for idx in 0..2 {
+            let own = position.history[idx].iter().flatten().filter(|field| **field == 1).count();
+            assert_eq!(plane_sum(4 + 2 * idx) as usize, own);
+            assert_eq!(plane_sum(5 + 2 * idx) as usize, 28 - own);
+        }
+        assert_eq!(plane_sum(8) + plane_sum(9) + plane_sum(10) + plane_sum(11), 0.0);
+        assert_eq!(plane_sum(12), 121.0);
+        assert!((plane_sum(13) - 121.0 * 0.04).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_model_registry() {
+        let registry = ModelRegistry::new();
+        let signature = config::ModelSignature::default();
+        let loads = AtomicUsize::new(0);
+        let load = || {
+            loads.fetch_add(1, Ordering::SeqCst);
+            FirstMoveModel
+        };
+        let first = registry.get_or_load("checkpoint_1", &signature, load);
+        let second = registry.get_or_load("checkpoint_1", &signature, load);
+        assert!(Arc::ptr_eq(&first, &second));
+        let other = registry.get_or_load("checkpoint_2", &signature, load);
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+        assert_eq!(registry.len(), 2);
+        // two agents with different checkpoints share their models with the registry
+        let config = AgentConfig::builder().mcts_num(10).mcts_parallel(2).mcts_depth(3).build().unwrap();
+        let mut white = MagisterLudi::with_evaluator(BELGIAN_DAISY, config.clone(), first.clone());
+        let mut black = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, other.clone());
+        let black_move = black.own_move(false).board().unwrap();
+        white.external_move(black_move, false);
+        assert!(AbaloneGame::validate_board(white.own_move(false).board().unwrap()));
+        white.stop_execution();
+        black.stop_execution();
+        drop(white);
+        drop(black);
+        // a model is unloaded with its last user and loaded again on the next request
+        drop(other);
+        assert_eq!(registry.len(), 1);
+        assert!(registry.get("checkpoint_2", &signature).is_none());
+        registry.get_or_load("checkpoint_2", &signature, load);
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+        // a model that fails to load does not poison the registry
+        let failed = std::panic::catch_unwind(|| {
+            registry.get_or_load("checkpoint_3", &signature, || -> FirstMoveModel { panic!("missing model") })
+        });
+        assert!(failed.is_err());
+        assert!(registry.get("checkpoint_3", &signature).is_none());
+        let retried = registry.get_or_load("checkpoint_3", &signature, load);
+        assert!(registry.get("checkpoint_3", &signature).is_some_and(|model| Arc::ptr_eq(&model, &retried)));
+        assert_eq!(registry.len(), 2);
+    }
+
+    #[test]
+    fn test_model_store() {
+        let root = std::env::temp_dir().join(format!("rustai_abalone_store_{}", std::process::id()));
+        let source = root.join("checkpoint");
+        std::fs::create_dir_all(source.join("variables")).unwrap();
+        for file in ["saved_model.pb", "variables/variables.index"] {
+            std::fs::write(source.join(file), file).unwrap();
+        }
+        let store = ModelStore::new(root.join("cache"));
+        assert!(store.list().unwrap().is_empty());
+        assert!(matches!(store.install(&source, ModelManifest::new("../evil", "1")), Err(StoreError::InvalidName(_))));
+        let mut manifest = ModelManifest::new("history_net", "3");
+        manifest.signature.encoding = encoder::InputEncoding::History { history_len: 2, side_to_move: true, move_counter: false };
+        let model = store.install(&source, manifest.clone()).unwrap();
+        store.install(&source, ModelManifest::new("history_net", "2")).unwrap();
+        assert_eq!(model.manifest.files.len(), 2);
+        assert_eq!(model.manifest.signature, manifest.signature);
+        let versions: Vec<_> = store.list().unwrap().iter().map(|model| model.manifest.version.clone()).collect();
+        assert_eq!(versions, vec!["2", "3"]);
+
+        assert!(store.selected().unwrap().is_none());
+        store.select("history_net", "3").unwrap();
+        assert_eq!(store.selected().unwrap(), Some(model.clone()));
+        assert_eq!(store.default_model().unwrap(), model);
+        assert!(matches!(store.select("history_net", "4"), Err(StoreError::NotInstalled(_))));
+
+        store.verify(&model).unwrap();
+        std::fs::write(model.path.join("saved_model.pb"), "corrupted").unwrap();
+        assert!(matches!(store.verify(&model), Err(StoreError::Checksum { .. })));
+
+        store.remove("history_net", "3").unwrap();
+        assert!(store.selected().unwrap().is_none());
+        assert_eq!(store.list().unwrap().len(), 1);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "download")]
+    fn test_model_store_default() {
+        use store::{DEFAULT_MODEL_NAME, DEFAULT_MODEL_VERSION};
+        let root = std::env::temp_dir().join(format!("rustai_abalone_store_default_{}", std::process::id()));
+        let source = root.join("checkpoint");
+        for file in ["saved_model.pb", "keras_metadata.pb", "fingerprint.pb", "variables/variables.index", "variables/variables.data-00000-of-00001"] {
+            std::fs::create_dir_all(source.join(file).parent().unwrap()).unwrap();
+            std::fs::write(source.join(file), file).unwrap();
+        }
+        let store = ModelStore::new(root.join("cache"));
+        let offline = DownloadOptions { offline: true, ..DownloadOptions::default() };
+        assert!(matches!(store.default_model_with(&offline), Err(StoreError::Download(DownloadError::Offline))));
+        let model = store.install(&source, ModelManifest::new(DEFAULT_MODEL_NAME, DEFAULT_MODEL_VERSION)).unwrap();
+        assert_eq!(store.default_model_with(&offline).unwrap(), model);
+        // a damaged model is not used, the error of the failed install is returned instead
+        std::fs::write(model.path.join("fingerprint.pb"), "corrupted").unwrap();
+        assert!(matches!(store.default_model_with(&offline), Err(StoreError::Download(DownloadError::Offline))));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_magister_ludi_seeded() {
+        // without threads the same seed yields the same moves
+        let play = || {
+            let config = AgentConfig::builder()
+                .mcts_num(30)
+                .mcts_parallel(0)
+                .mcts_depth(4)
+                .seed(7)
+                .move_selection(player::MoveSelection::self_play())
+                .build()
+                .unwrap();
+            let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+            let moves: Vec<_> = (0..3).map(|_| magi_ludi.own_move(true)).collect();
+            magi_ludi.stop_execution();
+            moves
+        };
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn test_magister_ludi_set_seed() {
+        let config = AgentConfig::builder()
+            .mcts_num(30)
+            .mcts_parallel(0)
+            .mcts_depth(4)
+            .move_selection(player::MoveSelection::self_play())
+            .build()
+            .unwrap();
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        let mut play = |seed| {
+            magi_ludi.start_new_game(BELGIAN_DAISY);
+            magi_ludi.set_seed(Some(seed));
+            let moves: Vec<_> = (0..3).map(|_| magi_ludi.own_move(true)).collect();
+            let visits: Vec<_> = magi_ludi.last_search_report().unwrap().children.iter().map(|child| child.visits).collect();
+            (moves, visits)
+        };
+        // reseeding the same agent repeats its game, another seed plays differently
+        let first = play(11);
+        assert_eq!(play(11), first);
+        assert_ne!(play(12), first);
+        magi_ludi.stop_execution();
+        // with threads every thread draws from its own stream
+        let config = AgentConfig::builder().mcts_num(30).mcts_parallel(3).mcts_depth(4).seed(11).build().unwrap();
+        let mut magi_ludi = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        assert_eq!(magi_ludi.config().seed, Some(11));
+        assert!(AbaloneGame::validate_board(magi_ludi.own_move(true).board().unwrap()));
+        magi_ludi.set_seed(None);
+        assert!(magi_ludi.check_threads_all_active());
+        magi_ludi.stop_execution();
+    }
+
+    #[test]
+    #[cfg(feature = "tensorflow")]
     fn test_magister_ludi_limited() {
         let mut magi_ludi = MagisterLudi::new(game::BELGIAN_DAISY, None, 10, 5, 1, 15);
         println!("initialized succesfully");
-        let chosen_move = magi_ludi.own_move(true);
+        let chosen_move = magi_ludi.own_move(true).board().unwrap();
         assert!(AbaloneGame::validate_board(chosen_move));
         println!("Finished move");
         magi_ludi.start_new_game(game::BELGIAN_DAISY);
@@ -132,10 +1102,149 @@ mod tests {
     }
 
     #[test]
+    fn test_magister_ludi_resign() {
+        // every value is below a threshold of 1, so the agent resigns at its first move
+        let config = AgentConfig::builder().mcts_num(10).mcts_parallel(2).mcts_depth(3).resign(1.0, 1).build().unwrap();
+        let mut magi_ludi = MagisterLudi::with_evaluator(game::BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        assert_eq!(magi_ludi.own_move(true), AgentAction::Resign);
+        assert!(magi_ludi.last_search_report().is_some());
+        // the resignation stopped the threads, a new game restarts them
+        magi_ludi.start_new_game(game::BELGIAN_DAISY);
+        assert!(magi_ludi.check_threads_all_active());
+        magi_ludi.stop_execution();
+    }
+
+    #[test]
+    fn test_self_play() {
+        let config = AgentConfig::builder()
+            .mcts_num(20)
+            .mcts_parallel(0)
+            .mcts_depth(3)
+            .seed(3)
+            .move_selection(player::MoveSelection::self_play())
+            .build()
+            .unwrap();
+        let agent = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+        let mut runner = SelfPlayRunner::new(agent, BELGIAN_DAISY, 8);
+        let game = runner.play_game();
+        assert_eq!(game.num_moves, 8);
+        assert_eq!(game.records.len(), 8);
+        // adjudicated games are draws for both sides
+        assert_eq!(game.result, 0);
+        assert_eq!(game.records[0].board, AbaloneGame::rotate_board(BELGIAN_DAISY));
+        for record in &game.records {
+            assert_eq!(record.policy.len(), NUM_MOVES);
+            assert!((record.policy.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+            assert_eq!(record.outcome, 0.0);
+        }
+        #[cfg(feature = "npz")]
+        {
+            let path = std::env::temp_dir().join(format!("rustai_abalone_test_shard_{}.npz", std::process::id()));
+            selfplay::write_shard(&path, &game.records).unwrap();
+            assert_eq!(selfplay::read_shard(&path).unwrap(), game.records);
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        // the first two moves come from a book and are not recorded
+        let mut opening = AbaloneGame::new(BELGIAN_DAISY);
+        for _ in 0..2 {
+            opening.calc_reasonalbe_moves();
+            opening.update_by_id(0);
+        }
+        let mut builder = BookBuilder::new(BookOptions::default());
+        assert_eq!(builder.add_game(opening.get_state_history()), 2);
+        runner.agent().set_opening_book(Some(Arc::new(builder.build())));
+        runner.set_history_len(2);
+        let game = runner.play_game();
+        assert_eq!(game.records.len(), 6);
+        for (idx, record) in game.records.iter().enumerate() {
+            assert_eq!(record.move_number, idx + 2);
+            assert_eq!(record.black_tomove, idx % 2 == 0);
+            assert_eq!(record.history.len(), 2);
+        }
+        // the previous boards are seen from the side to move
+        assert_eq!(game.records[1].history[0], AbaloneGame::rotate_board(game.records[0].board));
+        assert_eq!(game.records[2].history[1], game.records[0].board);
+        let encoder = HistoryEncoder { history_len: 2, side_to_move: true, move_counter: true };
+        let samples = TrainingSample::from_self_play(&encoder, &game, 3);
+        let planes = encoder.planes();
+        for (sample, record) in samples.iter().zip(&game.records) {
+            assert_eq!(sample.input, TrainingSample::new(&encoder, &record.position(), vec![], 0.0, 3).input);
+            assert_eq!(sample.metadata.move_number as usize, record.move_number);
+            // the first field holds the planes of the side to move and the move counter
+            assert_eq!(sample.input[planes - 2], if record.black_tomove { 1.0 } else { 0.0 });
+            assert_eq!(sample.input[planes - 1], record.move_number as f32 / 100.0);
+            // both previous boards are encoded from the start of the recorded game on
+            for plane in 4..8 {
+                assert!(sample.input.chunks_exact(planes).any(|field| field[plane] == 1.0));
+            }
+        }
+        #[cfg(feature = "npz")]
+        {
+            let path = std::env::temp_dir().join(format!("rustai_abalone_test_book_shard_{}.npz", std::process::id()));
+            selfplay::write_shard(&path, &game.records).unwrap();
+            assert_eq!(selfplay::read_shard(&path).unwrap(), game.records);
+            std::fs::remove_file(&path).unwrap();
+        }
+        runner.into_agent().stop_execution();
+    }
+
+    #[test]
+    fn test_training_symmetries() {
+        let mut abalone = AbaloneGame::from_position(game::GERMAN_DAISY, false, 0);
+        let (_, move_ids) = abalone.calc_reasonalbe_moves();
+        let next_positions: Vec<_> = (0..move_ids.len()).map(|idx| abalone.get_next_position(idx)).collect();
+        for symmetry in Symmetry::ALL {
+            // every symmetry permutes the move IDs
+            let mut targets: Vec<usize> = (0..NUM_MOVES).map(|move_id| symmetry.transform_move_id(move_id)).collect();
+            targets.sort_unstable();
+            assert!(targets.iter().enumerate().all(|(idx, target)| idx == *target));
+            // a transformed move leads to the transformed follow-up position
+            let mut transformed = AbaloneGame::from_position(symmetry.transform_board(&game::GERMAN_DAISY), false, 0);
+            let (_, transformed_ids) = transformed.calc_reasonalbe_moves();
+            assert_eq!(transformed_ids.len(), move_ids.len());
+            for (move_id, next_position) in move_ids.iter().zip(&next_positions) {
+                let expected = (symmetry.transform_move_id(*move_id), symmetry.transform_board(next_position));
+                assert!((0..transformed_ids.len())
+                    .any(|idx| (transformed_ids[idx], transformed.get_next_position(idx)) == expected));
+            }
+        }
+        let half_turn = Symmetry { rotation: 3, reflect: false };
+        let board = abalone.get_next_position(0);
+        assert_eq!(half_turn.transform_board(&board), AbaloneGame::switch_colors(AbaloneGame::rotate_board(board)));
+
+        let encoder = HistoryEncoder { history_len: 1, side_to_move: true, move_counter: false };
+        let mut policy = vec![0.0; NUM_MOVES];
+        policy[move_ids[0]] = 1.0;
+        let position = Position { board: game::GERMAN_DAISY, history: vec![board], black_tomove: true, num_moves: 1 };
+        let sample = TrainingSample::new(&encoder, &position, policy, -1.0, 7);
+        let reflected = Symmetry { rotation: 0, reflect: true };
+        for symmetry in Symmetry::ALL {
+            // transforming twice equals the combined symmetry
+            let twice = sample.transform(symmetry).transform(reflected);
+            assert_eq!(sample.transform(twice.metadata.symmetry), twice);
+            let moved = Position {
+                board: symmetry.transform_board(&position.board),
+                history: vec![symmetry.transform_board(&board)],
+                ..position.clone()
+            };
+            assert_eq!(sample.transform(symmetry).input, TrainingSample::new(&encoder, &moved, vec![], -1.0, 7).input);
+        }
+        #[cfg(feature = "npz")]
+        {
+            let path = std::env::temp_dir().join(format!("rustai_abalone_test_samples_{}.npz", std::process::id()));
+            assert_eq!(training::write_samples(&path, std::slice::from_ref(&sample), true).unwrap(), 12);
+            assert_eq!(training::read_samples(&path).unwrap(), sample.symmetries());
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "tensorflow")]
     fn test_magister_ludi_full() {
         let mut magi_ludi = MagisterLudi::new(game::BELGIAN_DAISY, None, 10, 5, 1, 0);
         println!("initialized succesfully");
-        let chosen_move = magi_ludi.own_move(true);
+        let chosen_move = magi_ludi.own_move(true).board().unwrap();
         assert!(AbaloneGame::validate_board(chosen_move));
         println!("Finished move");
         magi_ludi.start_new_game(game::BELGIAN_DAISY);
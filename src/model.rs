@@ -0,0 +1,362 @@
+#[cfg(feature = "tensorflow")]
+use tensorflow::{
+    Graph, Operation, SavedModelBundle, Session, SessionOptions, SessionRunArgs, Tensor,
+};
+#[cfg(feature = "onnx")]
+use tract_onnx::prelude::{
+    tvec, Datum, Framework, InferenceFact, InferenceModelExt, ToDim, TypedModel, TypedRunnableModel,
+};
+
+#[cfg(feature = "tensorflow")]
+use super::config::ModelSignature;
+#[cfg(any(feature = "tensorflow", feature = "onnx"))]
+use super::encoder::InputEncoder;
+#[cfg(feature = "onnx")]
+use super::encoder::OneHotEncoder;
+use super::evaluator::{Evaluator, Position};
+#[cfg(any(feature = "tensorflow", feature = "onnx"))]
+use super::game::BOARD_SIZE;
+
+/// number of distinct moves, the policy of a model covers all of them, see `marble_moves::MOVES_IDX`
+pub const NUM_MOVES: usize = 1506;
+
+/// output of a `PolicyValueModel` for a single position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prediction {
+    /// unnormalised log-probabilities for all `NUM_MOVES` move IDs
+    pub policy_logits: Vec<f32>,
+    /// evaluation between -1 and 1, 1 means a win for the side to move
+    pub value: f32,
+}
+
+impl Prediction {
+    /// returns the probabilities of the given moves by a softmax over their logits
+    ///
+    /// # Arguments
+    ///
+    /// * `move_ids` - IDs of the reasonable moves in the predicted position
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::model::{Prediction, NUM_MOVES};
+    /// let prediction = Prediction { policy_logits: vec![0.0; NUM_MOVES], value: 0.0 };
+    /// assert_eq!(prediction.priors(&[10, 20]), vec![0.5, 0.5]);
+    /// ```
+    pub fn priors(&self, move_ids: &[usize]) -> Vec<f32> {
+        let move_exp: Vec<f32> = move_ids.iter().map(|idx| self.policy_logits[*idx].exp()).collect();
+        let move_exp_sum: f32 = move_exp.iter().sum();
+        move_exp.iter().map(|val| val / move_exp_sum).collect()
+    }
+}
+
+/// neural network predicting move logits and an evaluation for positions
+///
+/// like for `Evaluator` the positions are given from the point of view of the side to move.
+/// Every model can be used as the evaluator of `MagisterLudi`.
+pub trait PolicyValueModel: Send + Sync {
+    /// predicts move logits and evaluation for a batch of positions
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - positions from the point of view of the side to move
+    ///
+    /// # Returns
+    ///
+    /// * `predictions` - one prediction per position in the same order
+    fn predict(&self, positions: &[Position]) -> Vec<Prediction>;
+
+    /// returns the number of previous boards the model's input contains
+    fn history_len(&self) -> usize {
+        0
+    }
+}
+
+impl<M: PolicyValueModel> Evaluator for M {
+    fn priors(&self, position: &Position, move_ids: &[usize]) -> Vec<f32> {
+        self.predict(std::slice::from_ref(position))[0].priors(move_ids)
+    }
+
+    fn value(&self, position: &Position) -> f32 {
+        self.predict(std::slice::from_ref(position))[0].value
+    }
+
+    fn history_len(&self) -> usize {
+        PolicyValueModel::history_len(self)
+    }
+}
+
+/// the trained tensorflow model in the SavedModel format
+#[cfg(feature = "tensorflow")]
+pub struct TensorflowModel {
+    session: Session,
+    _graph: Graph,
+    inp: Operation,
+    distr_out: Operation,
+    rating_out: Operation,
+    encoder: Box<dyn InputEncoder>,
+}
+
+#[cfg(feature = "tensorflow")]
+impl TensorflowModel {
+    /// loads the tensorflow model from the given path
+    ///
+    /// the input is encoded as given by the `encoding` of the signature,
+    /// see `load_with_encoder` for encoders that are not part of the crate
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path` - path to the folder containing `saved_model.pb`, see `util::check_model_present`
+    /// * `model_signature` - names of the model's input and outputs and its input encoding
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use rustai_abalone::config::ModelSignature;
+    /// use rustai_abalone::model::TensorflowModel;
+    /// let installed = rustai_abalone::store::ModelStore::default().default_model().unwrap();
+    /// let model = TensorflowModel::load(&installed.model_path(), &ModelSignature::default());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the model cannot be loaded or does not contain the signature names
+    pub fn load(model_path: &str, model_signature: &ModelSignature) -> Self {
+        Self::load_with_encoder(model_path, model_signature, model_signature.encoding.encoder())
+    }
+
+    /// loads the tensorflow model from the given path with a custom input encoding
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path` - path to the folder containing `saved_model.pb`, see `util::check_model_present`
+    /// * `model_signature` - names of the model's input and outputs, its `encoding` is ignored
+    /// * `encoder` - turns the positions into the input of the model
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use rustai_abalone::config::ModelSignature;
+    /// use rustai_abalone::encoder::HistoryEncoder;
+    /// use rustai_abalone::model::TensorflowModel;
+    /// let encoder = HistoryEncoder { history_len: 2, side_to_move: true, move_counter: false };
+    /// let model = TensorflowModel::load_with_encoder("my_model", &ModelSignature::default(), Box::new(encoder));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the model cannot be loaded or does not contain the signature names
+    pub fn load_with_encoder(
+        model_path: &str,
+        model_signature: &ModelSignature,
+        encoder: Box<dyn InputEncoder>,
+    ) -> Self {
+        let signature_input_parameter_name = model_signature.input.as_str();
+        let signature_output_distr_name = model_signature.policy_output.as_str();
+        let signature_output_rating_name = model_signature.value_output.as_str();
+
+        let mut graph = Graph::new();
+
+        // Load saved model bundle (session state + meta_graph data)
+        let bundle =
+            SavedModelBundle::load(&SessionOptions::new(), ["serve"], &mut graph, model_path)
+                .expect("Can't load saved model");
+
+        // Get signature metadata from the model bundle
+        let signature = bundle
+            .meta_graph_def()
+            .get_signature("serving_default")
+            .unwrap();
+
+        // Get input/output info
+        let input_info = signature.get_input(signature_input_parameter_name).unwrap();
+        let output_distr_info = signature.get_output(signature_output_distr_name).unwrap();
+        let output_rating_info = signature.get_output(signature_output_rating_name).unwrap();
+
+        // Get input/output ops from graph
+        let input_op = graph
+            .operation_by_name_required(&input_info.name().name)
+            .unwrap();
+        let output_distr_op = graph
+            .operation_by_name_required(&output_distr_info.name().name)
+            .unwrap();
+        let output_rating_op = graph
+            .operation_by_name_required(&output_rating_info.name().name)
+            .unwrap();
+
+        // Get the session from the loaded model bundle
+        Self {
+            session: bundle.session,
+            _graph: graph,
+            inp: input_op,
+            distr_out: output_distr_op,
+            rating_out: output_rating_op,
+            encoder,
+        }
+    }
+
+    // prepares the positions for tensorflow input
+    fn convert_positions_to_tensor(&self, positions: &[Position]) -> Tensor<f32> {
+        let planes = self.encoder.planes() as u64;
+        Tensor::new(&[positions.len() as u64, BOARD_SIZE as u64, BOARD_SIZE as u64, planes])
+            .with_values(&self.encoder.encode_batch(positions))
+            .unwrap()
+    }
+}
+
+#[cfg(feature = "tensorflow")]
+impl PolicyValueModel for TensorflowModel {
+    fn predict(&self, positions: &[Position]) -> Vec<Prediction> {
+        if positions.is_empty() {
+            return Vec::new();
+        }
+        let tensor = self.convert_positions_to_tensor(positions);
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&self.inp, 0, &tensor); // Add any inputs
+
+        // Request outputs
+        let distr_fetch = args.request_fetch(&self.distr_out, 0);
+        let rating_fetch = args.request_fetch(&self.rating_out, 1);
+
+        // Run model
+        self.session
+            .run(&mut args) // Pass to session to run
+            .expect("Error occurred during calculations");
+
+        // Fetch outputs after graph execution
+        let move_logits: Tensor<f32> = args.fetch(distr_fetch).unwrap();
+        let ratings: Tensor<f32> = args.fetch(rating_fetch).unwrap();
+        move_logits
+            .chunks(NUM_MOVES)
+            .zip(ratings.iter())
+            .map(|(logits, value)| Prediction {
+                policy_logits: logits.to_vec(),
+                value: *value,
+            })
+            .collect()
+    }
+
+    fn history_len(&self) -> usize {
+        self.encoder.history_len()
+    }
+}
+
+/// the model exported to ONNX, run by the pure-Rust runtime tract
+///
+/// the exported model must take the same `[positions, 11, 11, planes]` input as the tensorflow
+/// model, see `encoder::InputEncoder`, and return the policy and the value head. The heads are
+/// told apart by their size. The SavedModel can be exported with
+/// `python -m tf2onnx.convert --saved-model magister_zero_unwrap_save --output magister_zero.onnx`
+#[cfg(feature = "onnx")]
+pub struct OnnxModel {
+    plan: TypedRunnableModel<TypedModel>,
+    encoder: Box<dyn InputEncoder>,
+}
+
+#[cfg(feature = "onnx")]
+impl OnnxModel {
+    /// loads and optimizes the ONNX model from the given file, the input is one-hot encoded
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path` - path to the `.onnx` file
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::sync::Arc;
+    /// use rustai_abalone::config::AgentConfig;
+    /// use rustai_abalone::model::OnnxModel;
+    /// use rustai_abalone::player::MagisterLudi;
+    /// let model = Arc::new(OnnxModel::load("magister_zero.onnx"));
+    /// let mut magister_ludi = MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, AgentConfig::default(), model);
+    /// # magister_ludi.stop_execution();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the file cannot be read or the model does not accept the board input
+    pub fn load(model_path: &str) -> Self {
+        Self::load_with_encoder(model_path, Box::new(OneHotEncoder))
+    }
+
+    /// loads and optimizes the ONNX model from the given file with a custom input encoding
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path` - path to the `.onnx` file
+    /// * `encoder` - turns the positions into the input of the model
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use rustai_abalone::encoder::InputEncoding;
+    /// use rustai_abalone::model::OnnxModel;
+    /// let encoding = InputEncoding::History { history_len: 2, side_to_move: true, move_counter: true };
+    /// let model = OnnxModel::load_with_encoder("history.onnx", encoding.encoder());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the file cannot be read or the model does not accept the encoded input
+    pub fn load_with_encoder(model_path: &str, encoder: Box<dyn InputEncoder>) -> Self {
+        let model = tract_onnx::onnx()
+            .model_for_path(model_path)
+            .expect("Can't load ONNX model");
+        // the batch size is left open
+        let batch = model.symbol_table.sym("N");
+        let input_fact = InferenceFact::dt_shape(
+            f32::datum_type(),
+            tvec!(batch.to_dim(), BOARD_SIZE.to_dim(), BOARD_SIZE.to_dim(), encoder.planes().to_dim()),
+        );
+        let plan = model
+            .with_input_fact(0, input_fact)
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .expect("Can't prepare ONNX model");
+        Self { plan, encoder }
+    }
+}
+
+#[cfg(feature = "onnx")]
+impl PolicyValueModel for OnnxModel {
+    fn predict(&self, positions: &[Position]) -> Vec<Prediction> {
+        if positions.is_empty() {
+            return Vec::new();
+        }
+        let input = tract_onnx::prelude::Tensor::from_shape(
+            &[positions.len(), BOARD_SIZE, BOARD_SIZE, self.encoder.planes()],
+            &self.encoder.encode_batch(positions),
+        )
+        .unwrap();
+        let outputs = self
+            .plan
+            .run(tvec!(input.into()))
+            .expect("Error occurred during calculations");
+        let mut move_logits: &[f32] = &[];
+        let mut ratings: &[f32] = &[];
+        for output in outputs.iter() {
+            let values = output.as_slice::<f32>().expect("Model outputs must be f32");
+            if values.len() == positions.len() * NUM_MOVES {
+                move_logits = values;
+            } else if values.len() == positions.len() {
+                ratings = values;
+            }
+        }
+        if move_logits.is_empty() || ratings.is_empty() {
+            panic!("ONNX model must return the policy and the value head");
+        }
+        move_logits
+            .chunks(NUM_MOVES)
+            .zip(ratings.iter())
+            .map(|(logits, value)| Prediction {
+                policy_logits: logits.to_vec(),
+                value: *value,
+            })
+            .collect()
+    }
+
+    fn history_len(&self) -> usize {
+        self.encoder.history_len()
+    }
+}
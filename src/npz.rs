@@ -0,0 +1,269 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// magic string at the start of every `.npy` file
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// the header of a `.npy` file is padded so that the data starts at a multiple of this
+const NPY_ALIGNMENT: usize = 64;
+
+/// error while reading or writing NPZ files
+#[derive(Debug)]
+pub enum NpzError {
+    /// the file could not be read or written
+    Io(io::Error),
+    /// the file is not a valid zip archive or misses an array
+    Zip(ZipError),
+    /// an array has an unsupported format or another element type than requested
+    Format(String),
+}
+
+impl fmt::Display for NpzError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access NPZ file: {err}"),
+            Self::Zip(err) => write!(f, "invalid NPZ file: {err}"),
+            Self::Format(msg) => write!(f, "invalid NPY array: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for NpzError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Zip(err) => Some(err),
+            Self::Format(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for NpzError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ZipError> for NpzError {
+    fn from(err: ZipError) -> Self {
+        Self::Zip(err)
+    }
+}
+
+/// element type of an NPY array, stored in little-endian byte order
+pub trait NpyElement: Copy {
+    /// type description of numpy, e.g. `<f4`
+    const DESCR: &'static str;
+    /// number of bytes of an element
+    const SIZE: usize;
+
+    /// appends the little-endian bytes of the element
+    fn write_le(self, out: &mut Vec<u8>);
+
+    /// reads the element from its `SIZE` little-endian bytes
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_npy_element {
+    ($($ty:ty => $descr:expr),*) => {
+        $(
+            impl NpyElement for $ty {
+                const DESCR: &'static str = $descr;
+                const SIZE: usize = std::mem::size_of::<$ty>();
+
+                fn write_le(self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_le(bytes: &[u8]) -> Self {
+                    <$ty>::from_le_bytes(bytes.try_into().unwrap())
+                }
+            }
+        )*
+    };
+}
+
+impl_npy_element!(i8 => "|i1", u8 => "|u1", i32 => "<i4", u32 => "<u4", u64 => "<u8", f32 => "<f4");
+
+/// n-dimensional array in row-major order as it is stored in an `.npy` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpyArray<T> {
+    /// size of every dimension
+    pub shape: Vec<usize>,
+    /// elements in row-major order
+    pub data: Vec<T>,
+}
+
+// serialises an array in the NPY format version 1.0
+fn encode_npy<T: NpyElement>(shape: &[usize], data: &[T]) -> Vec<u8> {
+    let shape_str = match shape {
+        [single] => format!("({single},)"),
+        _ => format!("({})", shape.iter().map(|dim| dim.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {shape_str}, }}", T::DESCR);
+    // magic, version and header length take 10 bytes, the header ends with a newline
+    let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+    header.push_str(&" ".repeat((NPY_ALIGNMENT - unpadded % NPY_ALIGNMENT) % NPY_ALIGNMENT));
+    header.push('\n');
+    let mut bytes = Vec::with_capacity(NPY_MAGIC.len() + 4 + header.len() + data.len() * T::SIZE);
+    bytes.extend_from_slice(NPY_MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    for value in data {
+        value.write_le(&mut bytes);
+    }
+    bytes
+}
+
+// returns the value of a key in the header dictionary of an NPY file
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, NpzError> {
+    let missing = || NpzError::Format(format!("header misses '{key}'"));
+    let start = header.find(&format!("'{key}':")).ok_or_else(missing)? + key.len() + 3;
+    let rest = header[start..].trim_start();
+    // the shape is a tuple, all other values end with the next comma
+    let end = if rest.starts_with('(') { rest.find(')').map(|idx| idx + 1) } else { rest.find([',', '}']) };
+    Ok(rest[..end.ok_or_else(missing)?].trim())
+}
+
+// parses an array in the NPY format version 1.0 or 2.0
+fn decode_npy<T: NpyElement>(bytes: &[u8]) -> Result<NpyArray<T>, NpzError> {
+    if !bytes.starts_with(NPY_MAGIC) || bytes.len() < 10 {
+        return Err(NpzError::Format("missing NPY magic string".to_string()));
+    }
+    let (header_start, header_len) = match bytes[6] {
+        1 => (10, u16::from_le_bytes([bytes[8], bytes[9]]) as usize),
+        2 | 3 if bytes.len() >= 12 => (12, u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize),
+        version => return Err(NpzError::Format(format!("unsupported NPY version {version}"))),
+    };
+    let header = bytes
+        .get(header_start..header_start + header_len)
+        .and_then(|header| std::str::from_utf8(header).ok())
+        .ok_or_else(|| NpzError::Format("invalid header".to_string()))?;
+    let descr = header_value(header, "descr")?.trim_matches('\'');
+    // single byte types may be stored with any byte order marker
+    if descr != T::DESCR && !(T::SIZE == 1 && descr.get(1..) == T::DESCR.get(1..)) {
+        return Err(NpzError::Format(format!("element type {descr} instead of {}", T::DESCR)));
+    }
+    if header_value(header, "fortran_order")? != "False" {
+        return Err(NpzError::Format("fortran order is not supported".to_string()));
+    }
+    let shape = header_value(header, "shape")?
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse::<usize>().map_err(|_| NpzError::Format(format!("invalid dimension {dim}"))))
+        .collect::<Result<Vec<usize>, NpzError>>()?;
+    let data_bytes = &bytes[header_start + header_len..];
+    let len: usize = shape.iter().product();
+    if data_bytes.len() != len * T::SIZE {
+        return Err(NpzError::Format(format!("{} bytes of data for shape {shape:?}", data_bytes.len())));
+    }
+    let data = data_bytes.chunks_exact(T::SIZE).map(T::read_le).collect();
+    Ok(NpyArray { shape, data })
+}
+
+/// writes arrays to an NPZ file, the format of `numpy.savez_compressed`
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::npz::{NpzReader, NpzWriter};
+/// let path = std::env::temp_dir().join("rustai_abalone_doc.npz");
+/// let mut writer = NpzWriter::create(&path).unwrap();
+/// writer.add_array("values", &[2, 2], &[1.0f32, 2.0, 3.0, 4.0]).unwrap();
+/// writer.finish().unwrap();
+/// let values = NpzReader::open(&path).unwrap().read_array::<f32>("values").unwrap();
+/// assert_eq!(values.shape, vec![2, 2]);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct NpzWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+}
+
+impl NpzWriter<BufWriter<File>> {
+    /// creates the NPZ file at the given path, an existing file is overwritten
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, NpzError> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write + Seek> NpzWriter<W> {
+    /// creates a writer for NPZ data
+    pub fn new(writer: W) -> Self {
+        Self {
+            zip: ZipWriter::new(writer),
+        }
+    }
+
+    /// adds an array, numpy loads it under the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the array without the `.npy` extension
+    /// * `shape` - size of every dimension
+    /// * `data` - elements in row-major order
+    ///
+    /// # Panics
+    ///
+    /// will panic if the number of elements does not match the shape
+    pub fn add_array<T: NpyElement>(&mut self, name: &str, shape: &[usize], data: &[T]) -> Result<(), NpzError> {
+        assert_eq!(shape.iter().product::<usize>(), data.len(), "shape does not match the number of elements");
+        self.zip.start_file(format!("{name}.npy"), SimpleFileOptions::default())?;
+        self.zip.write_all(&encode_npy(shape, data))?;
+        Ok(())
+    }
+
+    /// finishes the archive and returns the underlying writer
+    pub fn finish(self) -> Result<W, NpzError> {
+        Ok(self.zip.finish()?)
+    }
+}
+
+/// reads arrays from an NPZ file as written by `numpy.savez` or `NpzWriter`
+pub struct NpzReader<R: Read + Seek> {
+    zip: ZipArchive<R>,
+}
+
+impl NpzReader<BufReader<File>> {
+    /// opens the NPZ file at the given path
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, NpzError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> NpzReader<R> {
+    /// creates a reader for NPZ data
+    pub fn new(reader: R) -> Result<Self, NpzError> {
+        Ok(Self {
+            zip: ZipArchive::new(reader)?,
+        })
+    }
+
+    /// returns the names of the arrays without the `.npy` extension
+    pub fn names(&self) -> Vec<String> {
+        self.zip
+            .file_names()
+            .filter_map(|name| name.strip_suffix(".npy"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// reads the array with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the array without the `.npy` extension
+    pub fn read_array<T: NpyElement>(&mut self, name: &str) -> Result<NpyArray<T>, NpzError> {
+        let mut file = self.zip.by_name(&format!("{name}.npy"))?;
+        let mut bytes = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut bytes)?;
+        decode_npy(&bytes)
+    }
+}
@@ -1,70 +1,507 @@
 use crate::game::{AbaloneGame, Board};
 use rand::distributions::WeightedIndex;
-use rand::prelude::{thread_rng, Distribution};
+use rand::prelude::{Distribution, Rng, SeedableRng, StdRng};
 use rand::seq::SliceRandom;
+use rand_distr::Dirichlet;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::sync::Arc;
+use crossbeam_channel::{select, unbounded, Receiver, RecvTimeoutError, Sender};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use std::{thread, time};
-use tensorflow::{
-    Graph, Operation, SavedModelBundle, Session, SessionOptions, SessionRunArgs, Tensor,
-};
 
+use super::book::OpeningBook;
+use super::cache::{CacheStats, DistributionCache};
+use super::config::{AgentConfig, ConfigError};
+use super::evaluator::{Evaluator, Position};
 use super::game;
+use super::model::NUM_MOVES;
+#[cfg(feature = "tensorflow")]
+use super::model::TensorflowModel;
+#[cfg(feature = "tensorflow")]
+use super::registry::ModelRegistry;
+#[cfg(feature = "tensorflow")]
+use super::store::ModelStore;
+#[cfg(feature = "tensorflow")]
 use super::util;
 
+/// evaluator `MagisterLudi` uses if no other is named, any evaluator behind a trait object.
+/// It is the same with and without the `tensorflow` feature, so enabling features never changes types
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use rustai_abalone::config::AgentConfig;
+/// use rustai_abalone::evaluator::HeuristicEvaluator;
+/// use rustai_abalone::player::MagisterLudi;
+/// let config = AgentConfig::builder().mcts_num(10).mcts_parallel(0).mcts_depth(3).build().unwrap();
+/// let mut agents: Vec<MagisterLudi> = vec![MagisterLudi::with_evaluator(
+///     rustai_abalone::game::BELGIAN_DAISY,
+///     config,
+///     Arc::new(HeuristicEvaluator::default()),
+/// )];
+/// assert!(agents[0].own_move(true).board().is_some());
+/// # agents[0].stop_execution();
+/// ```
+pub type DefaultEvaluator = dyn Evaluator;
+
 /// agent that can play Abalone
-pub struct MagisterLudi {
+///
+/// the search is generic over the evaluator of positions, see `DefaultEvaluator`
+pub struct MagisterLudi<M: Evaluator + ?Sized = DefaultEvaluator> {
     /// abalone game instance that the agent uses for internal representation
     abalone: game::AbaloneGame,
-    /// stored path to the tensorflow model
-    model_path: String,
+    /// loads the evaluator from the configured model path and signature, None if the agent was given its own evaluator
+    model_loader: Option<fn(&AgentConfig) -> Arc<M>>,
     /// sender and receiver for thread manipulation
     stop_sender: Sender<bool>,
     stop_receiver: Receiver<bool>,
-    /// tensorflow session for the main thread
-    main_session: Session,
-    /// tensorflow input operation for the main thread
-    main_inp: Operation,
-    /// tensorflow output operation for the action distribution within the main thread
-    main_distr_out: Operation,
-    /// number of leafs to be reached for the MCTS
-    mcts_num: usize,
-    /// number of threads used for the MCTS
-    mcts_parallel: usize,
-    /// denotes whether the current MCTS is finished or not
-    mcts_finished: Arc<Mutex<usize>>,
-    /// minimum number a follow-up state must be selected from the root state
-    mcts_minimum: usize,
-    /// denotes how many moves will be played until the expansion will be evaluated.
-    /// If 0 the game will be played until the end.
-    mcts_depth: usize,
+    /// evaluates positions for the search, shared with the MCTS threads
+    evaluator: Arc<M>,
+    /// search parameters, see `AgentConfig`
+    config: AgentConfig,
     /// join handles for the MCTS threads
     mcts_handles: Vec<JoinHandle<()>>,
-    /// map for storing the leaf results according to the selected position from the root state
-    mcts_results: Arc<Mutex<HashMap<game::Board, f32>>>,
-    /// map for storing the
-    mcts_counts: HashMap<game::Board, f32>,
-    /// map for storing
-    mcts_board_ids: HashMap<usize, usize>,
+    /// map for storing the leaf results according to the index of the selected position from the root state
+    mcts_results: HashMap<usize, ChildResults>,
+    /// number of times each position from the root state was selected, ordered like the move IDs
+    mcts_board_ids: Vec<usize>,
+    /// move IDs of the follow-up positions of the root state
+    mcts_move_ids: Vec<usize>,
+    /// predicted probabilities of the follow-up positions of the root state
+    mcts_priors: Vec<f32>,
+    /// statistics of the last search
+    last_report: Option<SearchReport>,
+    /// book the agent plays from before it searches, shared between agents
+    opening_book: Option<Arc<OpeningBook>>,
+    /// number of consecutive own moves with an evaluation below the resignation threshold
+    resign_streak: usize,
+    /// number of consecutive own moves with an evaluation within the draw threshold
+    draw_streak: usize,
+    /// random number generator for the main thread
+    rng: StdRng,
     /// stores the distribution for a vistied state, as calcuating distributions is expansive
-    saved_distr: Arc<Mutex<HashMap<game::Board, WeightedIndex<f32>>>>,
-    /// vector that stores the selected position which will be simulated
-    game_queue: Arc<Mutex<Vec<(game::AbaloneGame, game::Board)>>>,
+    saved_distr: Arc<DistributionCache>,
+    /// sender and receiver for the positions which will be simulated by the MCTS threads
+    job_sender: Sender<LeafJob>,
+    job_receiver: Receiver<LeafJob>,
+    /// sender and receiver for the simulation results of the MCTS threads
+    result_sender: Sender<LeafResult>,
+    result_receiver: Receiver<LeafResult>,
+    /// pool that simulates the leafs instead of own MCTS threads, shared between agents
+    search_pool: Option<Arc<SearchPool<M>>>,
+}
+
+/// result of a turn of the agent, see `MagisterLudi::own_move`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AgentAction {
+    /// the agent moved to the contained state
+    Move(Board),
+    /// the agent moved to the contained state and offers a draw,
+    /// the opponent declines by simply making its next move
+    MoveOfferingDraw(Board),
+    /// the agent resigned without moving, the game has ended
+    Resign,
+}
+
+impl AgentAction {
+    /// returns the state the agent moved to or None if it resigned
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::game::BELGIAN_DAISY;
+    /// use rustai_abalone::player::AgentAction;
+    /// assert_eq!(AgentAction::MoveOfferingDraw(BELGIAN_DAISY).board(), Some(BELGIAN_DAISY));
+    /// assert_eq!(AgentAction::Resign.board(), None);
+    /// ```
+    pub fn board(&self) -> Option<Board> {
+        match self {
+            Self::Move(board) | Self::MoveOfferingDraw(board) => Some(*board),
+            Self::Resign => None,
+        }
+    }
+}
+
+/// statistics for one follow-up position of the root state after a search
+#[derive(Debug, Clone)]
+pub struct ChildReport {
+    /// follow-up position from the point of view of the side to move at the root state
+    pub board: Board,
+    /// ID of the move leading to this position, see `marble_moves::MOVES_IDX`
+    pub move_id: usize,
+    /// number of simulations started from this position
+    pub visits: usize,
+    /// averaged simulation result, 1 means a win and -1 a loss for the side to move at the root state
+    pub mean_value: f32,
+    /// probability the model assigned to this move before the search
+    pub prior: f32,
+    /// game states (not rotated) of the best simulation starting with this position
+    pub line: Vec<Board>,
+}
+
+/// statistics of a search for a single move
+#[derive(Debug, Clone)]
+pub struct SearchReport {
+    /// all simulated follow-up positions, sorted from best to worst mean value
+    pub children: Vec<ChildReport>,
+    /// game states (not rotated) of the best simulation for the best follow-up position
+    pub best_line: Vec<Board>,
+    /// number of positions visited during all simulations
+    pub nodes: usize,
+    /// time the search took
+    pub elapsed: Duration,
+    /// number of visited positions per second
+    pub nodes_per_second: f64,
+}
+
+impl SearchReport {
+    /// returns the statistics of the follow-up position with the best mean value
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// let chosen_state = magister_ludi.own_move(true).board().unwrap();
+    /// let report = magister_ludi.last_search_report().unwrap();
+    /// assert_eq!(report.best_child().unwrap().board, chosen_state);
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn best_child(&self) -> Option<&ChildReport> {
+        self.children.first()
+    }
+
+    /// returns the share of the simulations that started with each move, indexed by move ID
+    ///
+    /// the distribution covers all `model::NUM_MOVES` move IDs and is the policy target
+    /// for training, moves that were not simulated have a share of 0
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// magister_ludi.own_move(true);
+    /// let distribution = magister_ludi.last_search_report().unwrap().visit_distribution();
+    /// assert_eq!(distribution.len(), rustai_abalone::model::NUM_MOVES);
+    /// assert!((distribution.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn visit_distribution(&self) -> Vec<f32> {
+        let mut distribution = vec![0.0; NUM_MOVES];
+        let total: usize = self.children.iter().map(|child| child.visits).sum();
+        for child in self.children.iter() {
+            distribution[child.move_id] = child.visits as f32 / total as f32;
+        }
+        distribution
+    }
+}
+
+/// options how the agent selects positions to simulate and the move to play
+///
+/// the default chooses the move with the best averaged simulation result and draws
+/// the simulated positions from the raw move distribution of the model
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MoveSelection {
+    /// pairs of the first move number (counted from the start of the game) and the temperature
+    /// used from then on, sorted by the move number. Before the first entry the temperature is 0.
+    /// With temperature `t` a move is drawn with a probability proportional to `exp(mean_value / t)`,
+    /// with 0 the move with the best mean value is played
+    pub temperature_schedule: Vec<(usize, f32)>,
+    /// concentration parameter for the Dirichlet noise at the root state
+    pub dirichlet_alpha: f32,
+    /// share of the Dirichlet noise in the distribution the simulated positions are drawn from,
+    /// 0 disables the noise
+    pub dirichlet_epsilon: f32,
+    /// if true, the simulated positions are assigned in proportion to the model's distribution instead
+    /// of being drawn and the move with the best mean value is played without noise, temperature or blunders
+    pub greedy: bool,
+    /// probability that a uniformly drawn reasonable move is played instead of the searched one,
+    /// used to weaken the agent on purpose
+    pub blunder_probability: f32,
+}
+
+impl MoveSelection {
+    /// returns the options for deterministic greedy play
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::player::MoveSelection;
+    /// let selection = MoveSelection::greedy();
+    /// assert_eq!(selection.temperature(0), 0.0);
+    /// ```
+    pub fn greedy() -> Self {
+        Self {
+            greedy: true,
+            ..Self::default()
+        }
+    }
+
+    /// returns the options that are commonly used for self-play
+    ///
+    /// the first 30 moves are played with temperature 1 and Dirichlet noise with
+    /// `alpha = 0.3` makes up a quarter of the distribution at the root state
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::player::MoveSelection;
+    /// let selection = MoveSelection::self_play();
+    /// assert_eq!(selection.temperature(10), 1.0);
+    /// assert_eq!(selection.temperature(30), 0.0);
+    /// ```
+    pub fn self_play() -> Self {
+        Self {
+            temperature_schedule: vec![(0, 1.0), (30, 0.0)],
+            dirichlet_alpha: 0.3,
+            dirichlet_epsilon: 0.25,
+            greedy: false,
+            blunder_probability: 0.0,
+        }
+    }
+
+    /// returns the temperature for the given move number
+    ///
+    /// # Arguments
+    ///
+    /// * `num_moves` - number of moves made since the start of the game
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::player::MoveSelection;
+    /// let selection = MoveSelection {
+    ///     temperature_schedule: vec![(4, 1.0), (20, 0.5)],
+    ///     ..MoveSelection::default()
+    /// };
+    /// assert_eq!(selection.temperature(2), 0.0);
+    /// assert_eq!(selection.temperature(4), 1.0);
+    /// assert_eq!(selection.temperature(25), 0.5);
+    /// ```
+    pub fn temperature(&self, num_moves: usize) -> f32 {
+        if self.greedy {
+            return 0.0;
+        }
+        self.temperature_schedule
+            .iter()
+            .take_while(|(first_move, _)| *first_move <= num_moves)
+            .last()
+            .map_or(0.0, |(_, temperature)| *temperature)
+    }
+}
+
+/// limits for analysing a position with `MagisterLudi::analyse`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SearchBudget {
+    /// number of leafs to be reached for the MCTS
+    pub mcts_num: usize,
+    /// number of candidate moves to be returned
+    pub multi_pv: usize,
+}
+
+impl SearchBudget {
+    /// creates a new search budget
+    ///
+    /// # Arguments
+    ///
+    /// * `mcts_num` - number of leafs to be reached for the MCTS
+    /// * `multi_pv` - number of candidate moves to be returned
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::player::SearchBudget;
+    /// let budget = SearchBudget::new(200, 5);
+    /// ```
+    pub fn new(mcts_num: usize, multi_pv: usize) -> Self {
+        Self { mcts_num, multi_pv }
+    }
+
+    /// checks whether the budget allows a search that returns at least one move
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::player::SearchBudget;
+    /// assert!(SearchBudget::new(200, 5).validate().is_ok());
+    /// assert!(SearchBudget::new(0, 5).validate().is_err());
+    /// assert!(SearchBudget::new(200, 0).validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.mcts_num == 0 {
+            return Err(ConfigError::Invalid("mcts_num of the search budget must be at least 1".to_string()));
+        }
+        if self.multi_pv == 0 {
+            return Err(ConfigError::Invalid("multi_pv of the search budget must be at least 1".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// simulation result of a single leaf, send from the MCTS threads to the agent
+struct LeafResult {
+    /// index of the simulated follow-up position of the root state
+    child: usize,
+    /// simulation result from the point of view of the side to move at the root state
+    value: f32,
+    /// game states (not rotated) that were played during the simulation
+    line: Vec<Board>,
+    /// number of positions visited during the simulation
+    nodes: usize,
+}
+
+// accumulated simulation results for one follow-up position of the root state
+#[derive(Default)]
+struct ChildResults {
+    value_sum: f32,
+    visits: usize,
+    best_value: f32,
+    best_line: Vec<Board>,
+}
+
+/// position selected from the root state which will be simulated by one of the MCTS threads
+struct LeafJob {
+    /// copy of the game at the root state
+    game: game::AbaloneGame,
+    /// index of the child state of the root state that will be simulated
+    child: usize,
+    /// child state of the root state that will be simulated
+    board: game::Board,
+    /// denotes whether black was to move at the root state, the result is given from this side's view
+    root_black_tomove: bool,
+    /// root state and the states before it as they are on the board, the most recent first.
+    /// Only contains as many states as the evaluator needs for its history
+    recent: Vec<Board>,
+}
+
+/// leaf of an agent that is simulated by a `SearchPool`
+struct PoolJob<M: Evaluator + ?Sized> {
+    /// position that will be simulated
+    job: LeafJob,
+    /// evaluator of the agent the leaf belongs to
+    evaluator: Arc<M>,
+    /// distributions cached by the agent
+    saved_distr: Arc<DistributionCache>,
+    /// search depth of the agent
+    mcts_depth: usize,
+    /// sends the simulation result back to the agent
+    results: Sender<LeafResult>,
+}
+
+/// pool of MCTS threads shared by many agents
+///
+/// an agent created with `MagisterLudi::with_search_pool` starts no MCTS threads of its own and hands
+/// its leafs to the pool instead. The threads of the pool take the leafs of all agents from a single
+/// queue, so they keep working as long as any of the agents searches. Every leaf is simulated with
+/// the evaluator, cache and search depth of the agent it belongs to.
+///
+/// the threads are stopped when the pool is dropped, which happens after the last agent using it was dropped
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use rustai_abalone::config::AgentConfig;
+/// use rustai_abalone::evaluator::HeuristicEvaluator;
+/// use rustai_abalone::game::BELGIAN_DAISY;
+/// use rustai_abalone::player::{MagisterLudi, SearchPool};
+/// let pool = Arc::new(SearchPool::new(2, None));
+/// let evaluator = Arc::new(HeuristicEvaluator::default());
+/// let config = AgentConfig::builder().mcts_num(10).mcts_depth(3).build().unwrap();
+/// let mut first = MagisterLudi::with_search_pool(BELGIAN_DAISY, config.clone(), evaluator.clone(), pool.clone());
+/// let mut second = MagisterLudi::with_search_pool(BELGIAN_DAISY, config, evaluator, pool);
+/// assert!(first.own_move(true).board().is_some());
+/// assert!(second.own_move(true).board().is_some());
+/// ```
+pub struct SearchPool<M: Evaluator + ?Sized = DefaultEvaluator> {
+    /// sender for the leafs of all agents, the only sender of the queue
+    job_sender: Sender<PoolJob<M>>,
+    /// join handles for the threads of the pool
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl<M: Evaluator + ?Sized + 'static> SearchPool<M> {
+    /// creates a pool and starts its threads
+    ///
+    /// # Arguments
+    ///
+    /// * `threads` - number of threads simulating the leafs
+    /// * `seed` - seed every thread derives its random number generator from, None to seed them from system entropy
+    ///
+    /// # Panics
+    ///
+    /// will panic if `threads` is 0
+    pub fn new(threads: usize, seed: Option<u64>) -> Self {
+        assert!(threads > 0, "a search pool needs at least one thread");
+        let (job_sender, job_receiver) = unbounded::<PoolJob<M>>();
+        let handles = (0..threads)
+            .map(|i| {
+                let job_receiver = job_receiver.clone();
+                let thread_seed = seed.map(|seed| MagisterLudi::<M>::thread_seed(seed, i));
+                thread::spawn(move || {
+                    let mut rng = match thread_seed {
+                        Some(seed) => StdRng::seed_from_u64(seed),
+                        None => StdRng::from_entropy(),
+                    };
+                    // the queue is disconnected when the pool is dropped
+                    for PoolJob { job, evaluator, saved_distr, mcts_depth, results } in job_receiver.iter() {
+                        let leaf = MagisterLudi::simulate_leaf(&*evaluator, &saved_distr, mcts_depth, &mut rng, job);
+                        // an agent dropped during its search does not wait for the result anymore
+                        let _ = results.send(leaf);
+                    }
+                })
+            })
+            .collect();
+        Self { job_sender, handles }
+    }
+
+    /// returns the number of threads of the pool
+    pub fn threads(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// checks whether all threads of the pool are still active
+    pub fn check_threads_all_active(&self) -> bool {
+        self.handles.iter().all(|handle| !handle.is_finished())
+    }
+
+    // queues a leaf for the threads, they always outlive the sender
+    fn submit(&self, job: PoolJob<M>) {
+        self.job_sender.send(job).unwrap();
+    }
+}
+
+impl<M: Evaluator + ?Sized> Drop for SearchPool<M> {
+    fn drop(&mut self) {
+        // replacing the only sender disconnects the queue, so the threads finish their leafs and end
+        let (disconnected, _) = unbounded();
+        drop(std::mem::replace(&mut self.job_sender, disconnected));
+        for handle in self.handles.drain(..) {
+            // a panic of a thread was already noticed by the agent waiting for its leaf
+            let _ = handle.join();
+        }
+    }
 }
 
-impl MagisterLudi {
+#[cfg(feature = "tensorflow")]
+impl MagisterLudi<DefaultEvaluator> {
     /// creates a new agent instance and starts the necessary threads.
     /// Checks whether the required model is present in the given `model_path`.
     /// If it is not present it will be automatically downloaded.
     ///
+    /// all other parameters are set to the defaults of `AgentConfig`, use
+    /// `MagisterLudi::with_config` to change them
+    ///
     /// # Arguments
     ///
     /// * `board` - 11 x 11 array with the initial board position
-    /// * `model_path` - optional path to the stored tensorflow model, if None the library folder will be selected
+    /// * `model_path` - optional path to the stored tensorflow model, if None the default model of the
+    ///   per-user model store will be selected, see `store::ModelStore::default_model`
     /// * `mcts_num` - number of leafs for every MCTS
-    /// * `mcts_parallel` - number of threads for the MCTS
+    /// * `mcts_parallel` - number of threads for the MCTS, if 0 the simulations run on the calling thread
     /// * `mcts_minimum` - denotes how often a child state must at least be selected
     /// * `mcts_depth` - number of moves that will be simulated starting for the root state,
     ///     if 0 simulations run until the games end
@@ -79,8 +516,8 @@ impl MagisterLudi {
     /// 
     /// # Panics
     /// 
-    /// will panic if the provided model path does not exist or if the model
-    /// is not present and downloading it fails
+    /// will panic if the provided model path does not exist, if the model
+    /// is not present and downloading it fails or if the parameters are invalid
     pub fn new(
         board: game::Board,
         model_path: Option<&str>,
@@ -89,51 +526,304 @@ impl MagisterLudi {
         mcts_minimum: usize,
         mcts_depth: usize,
     ) -> Self {
-        // if no path is given the library path will be used
-        let model_path = match model_path {
-            Some(val) => val,
-            _ => &std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        let config = AgentConfig {
+            model_path: model_path.map(|path| path.to_string()),
+            mcts_num,
+            mcts_parallel,
+            mcts_minimum,
+            mcts_depth,
+            ..AgentConfig::default()
         };
+        Self::with_config(board, config)
+    }
+
+    /// creates a new agent instance from a configuration and starts the necessary threads.
+    /// Checks whether the required model is present in the configured `model_path`.
+    /// If it is not present it will be automatically downloaded. The model is shared
+    /// with all other agents using it, see `registry::ModelRegistry::global`.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - 11 x 11 array with the initial board position
+    /// * `config` - model location and search parameters
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::AgentConfig;
+    /// use rustai_abalone::player::MagisterLudi;
+    /// let config = AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap();
+    /// let mut magister_ludi = MagisterLudi::with_config(rustai_abalone::game::BELGIAN_DAISY, config);
+    /// # magister_ludi.stop_execution();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the provided model path does not exist, if the model
+    /// is not present and downloading it fails or if the configuration is invalid
+    pub fn with_config(board: game::Board, config: AgentConfig) -> Self {
+        if let Err(err) = config.validate() {
+            panic!("{err}");
+        }
+        let evaluator = Self::load_model(&config);
+        let mut mag_ludi = Self::with_evaluator(board, config, evaluator);
+        mag_ludi.model_loader = Some(Self::load_model);
+        mag_ludi
+    }
+
+    // loads the tensorflow model of the configuration, agents with the same model share it
+    fn load_model(config: &AgentConfig) -> Arc<DefaultEvaluator> {
+        let model: Arc<TensorflowModel> = match config.model_path.as_deref() {
+            Some(model_path) => {
+                let final_path = Self::resolve_model_path(model_path);
+                ModelRegistry::global().load(&final_path, &config.signature)
+            }
+            // if no path is given the default model of the store will be used
+            None => {
+                let model = ModelStore::default()
+                    .default_model()
+                    .unwrap_or_else(|err| panic!("No model available: {err}"));
+                ModelRegistry::global().load(&model.model_path(), &model.manifest.signature)
+            }
+        };
+        model
+    }
+
+    // finds the model in the given path and downloads it if it is not present
+    fn resolve_model_path(model_path: &str) -> String {
         // panics, if the given path does not exist
         // searches the path for required files and will download them if not present
-        let final_path = match util::check_model_present(model_path){
+        match util::check_model_present(model_path){
             Some(val) => val,
-            _ => {
-                // if the model is not present, download it
-                util::download_model(model_path);
-                // check whether the download worked, otherwise panic
-                match util::check_model_present(model_path) {
-                    Some(wal) => wal,
-                    _ => panic!("Downloading model did not work!")
-                }
-            }
-        };
+            #[cfg(feature = "download")]
+            // if the model is not present, download it
+            _ => match util::download_model(model_path) {
+                Ok(val) => val,
+                Err(err) => panic!("Model is not present in {model_path}: {err}"),
+            },
+            #[cfg(not(feature = "download"))]
+            _ => panic!("Model is not present in {model_path} and the download feature is disabled!"),
+        }
+    }
+}
+
+impl<M: Evaluator + ?Sized + 'static> MagisterLudi<M> {
+    /// creates a new agent instance that uses the given evaluator instead of the tensorflow model
+    ///
+    /// `model_path` and `signature` of the configuration are ignored, so no model
+    /// has to be present. With `HeuristicEvaluator` the agent runs without tensorflow,
+    /// any `PolicyValueModel` can be used as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - 11 x 11 array with the initial board position
+    /// * `config` - search parameters
+    /// * `evaluator` - evaluates positions for the search, it is shared with the MCTS threads
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use rustai_abalone::config::AgentConfig;
+    /// use rustai_abalone::evaluator::HeuristicEvaluator;
+    /// use rustai_abalone::player::MagisterLudi;
+    /// let config = AgentConfig::builder().mcts_num(20).mcts_parallel(2).mcts_depth(3).build().unwrap();
+    /// let mut magister_ludi = MagisterLudi::with_evaluator(
+    ///     rustai_abalone::game::BELGIAN_DAISY,
+    ///     config,
+    ///     Arc::new(HeuristicEvaluator::default()),
+    /// );
+    /// assert!(magister_ludi.own_move(true).board().is_some());
+    /// # magister_ludi.stop_execution();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the configuration is invalid
+    pub fn with_evaluator(board: game::Board, config: AgentConfig, evaluator: Arc<M>) -> Self {
+        if let Err(err) = config.validate() {
+            panic!("{err}");
+        }
         let (tx, rx) = unbounded();
-        let (session, _, inp, distr_out, _) = Self::create_session(&final_path);
+        let (job_tx, job_rx) = unbounded();
+        let (result_tx, result_rx) = unbounded();
         let mut mag_ludi = Self {
             abalone: game::AbaloneGame::new(board),
-            model_path: final_path.to_string(),
+            model_loader: None,
             stop_sender: tx,
             stop_receiver: rx,
-            main_session: session,
-            main_inp: inp,
-            main_distr_out: distr_out,
-            mcts_num,
-            mcts_parallel,
-            mcts_finished: Arc::new(Mutex::new(0)),
-            mcts_minimum,
-            mcts_depth,
-            mcts_handles: Vec::with_capacity(mcts_parallel),
-            mcts_results: Arc::new(Mutex::new(HashMap::new())),
-            mcts_counts: HashMap::new(),
-            mcts_board_ids: HashMap::new(),
-            saved_distr: Arc::new(Mutex::new(HashMap::with_capacity(mcts_num * 150 * 150))),
-            game_queue: Arc::new(Mutex::new(Vec::with_capacity(mcts_num))),
+            evaluator,
+            mcts_handles: Vec::with_capacity(config.mcts_parallel),
+            mcts_results: HashMap::new(),
+            mcts_board_ids: Vec::new(),
+            mcts_move_ids: Vec::new(),
+            mcts_priors: Vec::new(),
+            last_report: None,
+            opening_book: None,
+            resign_streak: 0,
+            draw_streak: 0,
+            rng: Self::create_rng(config.seed),
+            saved_distr: Arc::new(DistributionCache::new(config.cache_memory)),
+            job_sender: job_tx,
+            job_receiver: job_rx,
+            result_sender: result_tx,
+            result_receiver: result_rx,
+            search_pool: None,
+            config,
+        };
+        mag_ludi.start_threads();
+        mag_ludi
+    }
+
+    /// creates a new agent instance whose leafs are simulated by a shared search pool
+    ///
+    /// the agent starts no MCTS threads of its own, so `mcts_parallel` of the configuration
+    /// is ignored. The simulations draw from the random number generators of the pool threads,
+    /// with a seed only the choices of the agent itself are reproducible.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - 11 x 11 array with the initial board position
+    /// * `config` - search parameters
+    /// * `evaluator` - evaluates positions for the search, it is handed to the pool with every leaf
+    /// * `search_pool` - threads that simulate the leafs of this and other agents
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use rustai_abalone::config::AgentConfig;
+    /// use rustai_abalone::evaluator::HeuristicEvaluator;
+    /// use rustai_abalone::player::{MagisterLudi, SearchPool};
+    /// let config = AgentConfig::builder().mcts_num(20).mcts_depth(3).build().unwrap();
+    /// let mut magister_ludi = MagisterLudi::with_search_pool(
+    ///     rustai_abalone::game::BELGIAN_DAISY,
+    ///     config,
+    ///     Arc::new(HeuristicEvaluator::default()),
+    ///     Arc::new(SearchPool::new(2, None)),
+    /// );
+    /// assert!(magister_ludi.own_move(true).board().is_some());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the configuration is invalid
+    pub fn with_search_pool(board: game::Board, config: AgentConfig, evaluator: Arc<M>, search_pool: Arc<SearchPool<M>>) -> Self {
+        let config = AgentConfig {
+            mcts_parallel: 0,
+            ..config
         };
-        mag_ludi.start_threads(&final_path);
+        let mut mag_ludi = Self::with_evaluator(board, config, evaluator);
+        mag_ludi.search_pool = Some(search_pool);
         mag_ludi
     }
 
+    // creates a random number generator from the seed or from system entropy
+    fn create_rng(seed: Option<u64>) -> StdRng {
+        match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    /// returns the configuration the agent currently uses
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// assert_eq!(magister_ludi.config().mcts_num, 10);
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn config(&self) -> &AgentConfig {
+        &self.config
+    }
+
+    /// changes the configuration of the agent
+    ///
+    /// meant to be called between moves or games. The model is only reloaded if
+    /// its path or signature changed and the agent does not use its own evaluator,
+    /// the cached move distributions of the previous model are discarded then.
+    /// The MCTS threads are restarted with the new parameters, the game the agent
+    /// is playing stays as it is. An agent using a search pool keeps it and starts no threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - new model location and search parameters
+    ///
+    /// # Returns
+    ///
+    /// * `result` - error if the configuration is invalid, the agent is not changed then
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// let mut config = magister_ludi.config().clone();
+    /// config.mcts_num = 20;
+    /// magister_ludi.set_config(config).unwrap();
+    /// # magister_ludi.stop_execution();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the model at a changed path cannot be loaded
+    pub fn set_config(&mut self, mut config: AgentConfig) -> Result<(), ConfigError> {
+        config.validate()?;
+        if self.search_pool.is_some() {
+            config.mcts_parallel = 0;
+        }
+        self.stop_threads();
+        let model_changed = config.model_path != self.config.model_path || config.signature != self.config.signature;
+        if let Some(load_model) = self.model_loader.filter(|_| model_changed) {
+            self.replace_evaluator(load_model(&config));
+        }
+        if config.seed != self.config.seed {
+            self.rng = Self::create_rng(config.seed);
+        }
+        self.saved_distr.set_memory_cap(config.cache_memory);
+        self.config = config;
+        if !self.abalone.get_game_ended() {
+            self.start_threads();
+        }
+        Ok(())
+    }
+
+    /// replaces the evaluator of the agent
+    ///
+    /// the cached move distributions were calculated by the previous evaluator, so they are
+    /// discarded together with the statistics of the root state of the last search
+    ///
+    /// # Arguments
+    ///
+    /// * `evaluator` - new evaluator for the search, shared with the MCTS threads
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// magister_ludi.set_evaluator(std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// assert_eq!(magister_ludi.cache_stats().entries, 0);
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn set_evaluator(&mut self, evaluator: Arc<M>) {
+        self.stop_threads();
+        self.replace_evaluator(evaluator);
+        if !self.abalone.get_game_ended() {
+            self.start_threads();
+        }
+    }
+
+    // swaps the evaluator while the MCTS threads are stopped and drops everything it calculated
+    fn replace_evaluator(&mut self, evaluator: Arc<M>) {
+        self.evaluator = evaluator;
+        self.saved_distr.clear();
+        self.mcts_results.clear();
+        self.mcts_board_ids.clear();
+        self.mcts_move_ids.clear();
+        self.mcts_priors.clear();
+    }
+
     /// starts a new game for the agent
     /// 
     /// will start a fresh Abalone game for the given starting position and respawns
@@ -146,7 +836,7 @@ impl MagisterLudi {
     /// # Examples
     /// 
     /// ```rust
-    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::new(rustai_abalone::game::BELGIAN_DAISY, None, 10, 5, 1, 5);
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
     /// magister_ludi.start_new_game(rustai_abalone::game::BELGIAN_DAISY);
     /// # magister_ludi.stop_execution();
     /// ```
@@ -155,10 +845,11 @@ impl MagisterLudi {
             // be sure that all threads stopped
             self.stop_execution();
             // restart threads
-            let load_path = self.model_path.clone();
-            self.start_threads(&load_path);
+            self.start_threads();
         }
         self.abalone = game::AbaloneGame::new(board);
+        self.resign_streak = 0;
+        self.draw_streak = 0;
     }
 
     /// lets the agent know that a move was made by an external source and change its game representation accordingly
@@ -171,7 +862,7 @@ impl MagisterLudi {
     /// # Examples
     ///
     /// ```rust
-    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::new(rustai_abalone::game::BELGIAN_DAISY, None, 10, 5, 1, 5);
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
     /// let next_state = [
     ///     [3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3],
     ///     [3, 3, 3, 3, 3, 1, 1, 0, 2, 2, 3],
@@ -201,7 +892,12 @@ impl MagisterLudi {
         self.check_game_ended();
     }
 
-    /// lets the agent know that a move was made by an external source and change its game representation accordingly
+    /// lets the agent search the current position and make its move
+    ///
+    /// if an opening book is set and contains the current position, a book move is drawn by its
+    /// weight and played without search, see `set_opening_book`. If resignation or draw offers are enabled in the configuration, the evaluation
+    /// of the search decides whether the agent resigns instead of moving or offers a draw
+    /// together with its move. A resignation ends the game with a win for the opponent.
     /// 
     /// # Arguments
     /// 
@@ -209,82 +905,417 @@ impl MagisterLudi {
     ///
     /// # Returns
     ///
-    /// * `chosen_state` - the state the agent wants to reach with its move
+    /// * `action` - the state the agent wants to reach with its move, possibly with a draw offer,
+    ///   or its resignation
     ///
     /// # Examples
     ///
     /// ```rust
-    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::new(rustai_abalone::game::BELGIAN_DAISY, None, 10, 5, 1, 5);
-    /// let next_state = magister_ludi.own_move(true);
-    /// assert!(rustai_abalone::game::AbaloneGame::validate_board(next_state));
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// use rustai_abalone::player::AgentAction;
+    /// match magister_ludi.own_move(true) {
+    ///     AgentAction::Move(next_state) | AgentAction::MoveOfferingDraw(next_state) => {
+    ///         assert!(rustai_abalone::game::AbaloneGame::validate_board(next_state));
+    ///     }
+    ///     AgentAction::Resign => println!("the agent resigned"),
+    /// }
     /// # magister_ludi.stop_execution();
     /// ```
     /// 
     /// # Panics
     /// 
     /// will panic if one or more of the MCTS daemon threads are not active anymore
-    pub fn own_move(&mut self, return_pov: bool) -> Board {
+    pub fn own_move(&mut self, return_pov: bool) -> AgentAction {
         if !self.check_threads_all_active() {
             panic!("Cannot execute move as there are issues with the activity of MCTS threads")
         }
-        self.choose_possible_moves();
-        self.push_to_queue();
-        let chosen_state = self.choose_next_move();
+        if let Some(book_state) = self.book_move() {
+            self.abalone.update_state(book_state);
+            self.last_report = None;
+            self.check_game_ended();
+            let board = if return_pov { book_state } else { self.abalone.get_state() };
+            return AgentAction::Move(board);
+        }
+        // the search runs on a copy, so the internal game only changes by the chosen move
+        let mut root = self.abalone.mcts_copy();
+        let previous = self.previous_states();
+        let report = self.run_search(&mut root, &previous, self.config.mcts_num, self.config.move_selection.greedy);
+        let best_value = report.children[0].mean_value;
+        if self.should_resign(best_value) {
+            self.last_report = Some(report);
+            // the opponent of the side to move wins
+            let result = if self.abalone.get_black_tomove() { 1 } else { -1 };
+            self.abalone.end_with_result(result);
+            self.check_game_ended();
+            return AgentAction::Resign;
+        }
+        let offer_draw = self.should_offer_draw(best_value);
+        let selection = &self.config.move_selection;
+        let temperature = selection.temperature(self.abalone.get_num_moves());
+        let blunder = !selection.greedy && self.rng.gen::<f32>() < selection.blunder_probability;
+        let chosen_state = if blunder {
+            // every reasonable move of the root state is equally likely
+            root.get_next_position(self.rng.gen_range(0..self.mcts_move_ids.len()))
+        } else {
+            report.children[self.select_child(&report, temperature)].board
+        };
+        self.abalone.update_state(chosen_state);
+        self.last_report = Some(report);
         self.check_game_ended();
-        // chosen_state is from white's point of view
-        if return_pov {
-            return chosen_state;
+        // chosen_state is from white's point of view, otherwise return state as it is
+        let board = if return_pov { chosen_state } else { self.abalone.get_state() };
+        if offer_draw {
+            AgentAction::MoveOfferingDraw(board)
+        } else {
+            AgentAction::Move(board)
+        }
+    }
+
+    // draws a move of the opening book for the current state, the follow-up position is from white's point of view
+    fn book_move(&mut self) -> Option<Board> {
+        let book = self.opening_book.as_ref()?;
+        let move_id = book.choose(&self.abalone.get_state(), self.abalone.get_black_tomove(), &mut self.rng)?;
+        let mut root = self.abalone.mcts_copy();
+        let (_, move_ids) = root.calc_reasonalbe_moves();
+        // a book move that is not possible in the position is ignored
+        let idx = move_ids.iter().position(|id| *id == move_id)?;
+        Some(root.get_next_position(idx))
+    }
+
+    // counts the moves below the resignation threshold and decides whether to resign
+    fn should_resign(&mut self, best_value: f32) -> bool {
+        match self.config.resign_threshold {
+            Some(threshold) if best_value < threshold => self.resign_streak += 1,
+            _ => self.resign_streak = 0,
+        }
+        self.resign_streak > 0 && self.resign_streak >= self.config.resign_moves
+    }
+
+    // counts the moves within the draw threshold and decides whether to offer a draw,
+    // after an offer the agent waits the same number of moves before offering again
+    fn should_offer_draw(&mut self, best_value: f32) -> bool {
+        match self.config.draw_threshold {
+            Some(threshold) if best_value.abs() <= threshold => self.draw_streak += 1,
+            _ => self.draw_streak = 0,
+        }
+        if self.draw_streak > 0 && self.draw_streak >= self.config.draw_moves {
+            self.draw_streak = 0;
+            return true;
+        }
+        false
+    }
+
+    /// lets the agent decide whether it accepts a draw offered by its opponent
+    ///
+    /// the agent searches the current position and accepts if its evaluation is not
+    /// better than the configured draw threshold. Accepting ends the game as a draw.
+    /// Without a draw threshold in the configuration every offer is declined.
+    ///
+    /// # Returns
+    ///
+    /// * `accepted` - true if the agent accepted the draw
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// if magister_ludi.consider_draw_offer() {
+    ///     println!("the game was drawn");
+    /// }
+    /// # magister_ludi.stop_execution();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if one or more of the MCTS daemon threads are not active anymore
+    pub fn consider_draw_offer(&mut self) -> bool {
+        let Some(threshold) = self.config.draw_threshold else {
+            return false;
+        };
+        if self.abalone.get_game_ended() {
+            return false;
+        }
+        if !self.check_threads_all_active() {
+            panic!("Cannot consider draw offer as there are issues with the activity of MCTS threads")
+        }
+        let mut root = self.abalone.mcts_copy();
+        let previous = self.previous_states();
+        let report = self.run_search(&mut root, &previous, self.config.mcts_num, self.config.move_selection.greedy);
+        let accepted = report.children[0].mean_value <= threshold;
+        self.last_report = Some(report);
+        if accepted {
+            self.abalone.end_with_result(0);
+            self.check_game_ended();
+        }
+        accepted
+    }
+
+    /// seeds the random number generators of the agent and its MCTS threads
+    ///
+    /// every MCTS thread receives its own stream derived from the seed. As the threads
+    /// pick up positions in varying order, only an agent with `mcts_parallel == 0`,
+    /// which runs all simulations on the calling thread, is fully reproducible:
+    /// the same seed will then yield the same moves. The MCTS threads are restarted.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - seed for the random number generators, if None they are seeded from system entropy
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(0).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// magister_ludi.set_seed(Some(42));
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.config.seed = seed;
+        self.rng = Self::create_rng(seed);
+        if !self.abalone.get_game_ended() {
+            self.stop_threads();
+            self.start_threads();
+        }
+    }
+
+    /// changes how the agent selects the positions to simulate and the move to play
+    ///
+    /// # Arguments
+    ///
+    /// * `move_selection` - temperature schedule, Dirichlet noise and greedy mode
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::player::MoveSelection;
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// magister_ludi.set_move_selection(MoveSelection::self_play());
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn set_move_selection(&mut self, move_selection: MoveSelection) {
+        self.config.move_selection = move_selection;
+    }
+
+    /// returns the options how the agent selects the positions to simulate and the move to play
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// assert!(!magister_ludi.move_selection().greedy);
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn move_selection(&self) -> &MoveSelection {
+        &self.config.move_selection
+    }
+
+    /// sets the opening book the agent plays from, None disables the book
+    ///
+    /// as long as the current position is in the book, `own_move` plays a book move drawn by
+    /// its weight instead of searching. Once the game leaves the book the agent searches every move.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// use std::sync::Arc;
+    /// use rustai_abalone::book::OpeningBook;
+    /// let book = OpeningBook::load("opening_book.json").unwrap_or_default();
+    /// magister_ludi.set_opening_book(Some(Arc::new(book)));
+    /// assert!(magister_ludi.opening_book().is_some());
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn set_opening_book(&mut self, book: Option<Arc<OpeningBook>>) {
+        self.opening_book = book;
+    }
+
+    /// returns the opening book the agent plays from
+    pub fn opening_book(&self) -> Option<&OpeningBook> {
+        self.opening_book.as_deref()
+    }
+
+    /// returns the statistics of the search for the last move made by the agent
+    ///
+    /// # Returns
+    ///
+    /// * `report` - visits, values and priors of all simulated follow-up positions, the best line found
+    ///   and performance numbers, None if the agent did not move yet or played its last move from the opening book
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// magister_ludi.own_move(true);
+    /// let report = magister_ludi.last_search_report().unwrap();
+    /// for child in report.children.iter() {
+    ///     println!("move {}: {} visits, value {}, prior {}", child.move_id, child.visits, child.mean_value, child.prior);
+    /// }
+    /// println!("{} nodes/s", report.nodes_per_second);
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn last_search_report(&self) -> Option<&SearchReport> {
+        self.last_report.as_ref()
+    }
+
+    /// analyses a position without making a move
+    ///
+    /// the position is searched like for `own_move`, but the game the agent is playing
+    /// is not changed. The follow-up positions are ranked by their averaged simulation results.
+    /// Independent of the move selection of the agent, the simulations are assigned in proportion
+    /// to the move distribution without Dirichlet noise like with `MoveSelection::greedy`.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - position to be analysed as it is on the board (not rotated)
+    /// * `black_tomove` - if true, black is to move in the given position, otherwise white
+    /// * `num_moves` - number of moves made before the given position, the evaluator can see it
+    /// * `budget` - number of leafs to simulate and number of candidate moves to return
+    ///
+    /// # Returns
+    ///
+    /// * `report` - search statistics whose `children` are the best candidate moves, best first.
+    ///   Values are given from the point of view of the side to move
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::game::BELGIAN_DAISY;
+    /// use rustai_abalone::player::SearchBudget;
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// let report = magister_ludi.analyse(BELGIAN_DAISY, false, 1, SearchBudget::new(50, 3));
+    /// assert!(report.children.len() <= 3);
+    /// for candidate in report.children.iter() {
+    ///     println!("move {}: value {} in {} visits", candidate.move_id, candidate.mean_value, candidate.visits);
+    /// }
+    /// # magister_ludi.stop_execution();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the budget is invalid, see `SearchBudget::validate`, if one or more of
+    /// the MCTS daemon threads are not active anymore or if the side to move has no moves left
+    /// in the given position
+    pub fn analyse(&mut self, board: Board, black_tomove: bool, num_moves: usize, budget: SearchBudget) -> SearchReport {
+        if let Err(err) = budget.validate() {
+            panic!("{err}");
+        }
+        if !self.check_threads_all_active() {
+            panic!("Cannot analyse position as there are issues with the activity of MCTS threads")
+        }
+        let mut root = AbaloneGame::from_position(board, black_tomove, num_moves);
+        // the states before the given position are unknown
+        let mut report = self.run_search(&mut root, &[], budget.mcts_num, true);
+        report.children.truncate(budget.multi_pv);
+        report
+    }
+
+    /// returns the game the agent is playing
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// assert_eq!(magister_ludi.game().get_state(), rustai_abalone::game::BELGIAN_DAISY);
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn game(&self) -> &AbaloneGame {
+        &self.abalone
+    }
+
+    // returns the states of the game before the current one, the most recent first,
+    // as far as the evaluator needs them
+    fn previous_states(&self) -> Vec<Board> {
+        self.abalone
+            .get_state_history()
+            .iter()
+            .rev()
+            .skip(1)
+            .take(self.evaluator.history_len())
+            .copied()
+            .collect()
+    }
+
+    // runs the MCTS for the given root state and collects the results,
+    // previous are the states before the root as they are on the board, the most recent first,
+    // greedy assigns the simulations in proportion to the priors without noise
+    fn run_search(&mut self, root: &mut AbaloneGame, previous: &[Board], mcts_num: usize, greedy: bool) -> SearchReport {
+        let search_start = Instant::now();
+        self.choose_possible_moves(root, previous, mcts_num, greedy);
+        self.push_to_queue(root, previous);
+        if let Some(search_pool) = &self.search_pool {
+            // the threads of the pool send the results back to this agent
+            while let Ok(job) = self.job_receiver.try_recv() {
+                search_pool.submit(PoolJob {
+                    job,
+                    evaluator: self.evaluator.clone(),
+                    saved_distr: self.saved_distr.clone(),
+                    mcts_depth: self.config.mcts_depth,
+                    results: self.result_sender.clone(),
+                });
+            }
+        } else if self.config.mcts_parallel == 0 {
+            // single threaded mode, the simulations run in the order they were queued
+            while let Ok(job) = self.job_receiver.try_recv() {
+                let leaf = Self::simulate_leaf(&*self.evaluator, &self.saved_distr, self.config.mcts_depth, &mut self.rng, job);
+                self.result_sender.send(leaf).unwrap();
+            }
         }
-        // otherwise return state as it is
-        self.abalone.get_state()
+        self.collect_results(root, mcts_num, search_start)
     }
 
     // selects the child state which should be simulated from the root state
-    fn choose_possible_moves(&mut self) {
-        let saved_distr = self.saved_distr.clone();
-        let mut rng = thread_rng();
-        self.mcts_board_ids.clear();
-        let (state, move_ids) = self.abalone.calc_reasonalbe_moves();
+    fn choose_possible_moves(&mut self, root: &mut AbaloneGame, previous: &[Board], mcts_num: usize, greedy: bool) {
+        let (state, move_ids) = root.calc_reasonalbe_moves();
+        let position = Position::from_game(root, previous, self.evaluator.history_len());
         // initialize 0 counts for all possible moves
         let mut board_ids: Vec<usize> = (0..move_ids.len()).collect();
-        for idx in &board_ids {
-            self.mcts_board_ids.insert(*idx, 0);
-        }
-        let mut distr_map = saved_distr.lock().unwrap();
-        // create distribution
-        let distr = match distr_map.get(&state) {
-            Some(distr) => distr,
+        self.mcts_board_ids.clear();
+        self.mcts_board_ids.resize(move_ids.len(), 0);
+        // create distribution, the probabilities are kept for the search report.
+        // Peeking does not count as a lookup, so the cache statistics only cover the simulations
+        self.mcts_priors = match self.saved_distr.peek_priors(&state) {
+            Some(priors) => priors,
             None => {
-                let distr = Self::calc_distribution(
-                    &self.main_session,
-                    &self.main_inp,
-                    &self.main_distr_out,
-                    &Self::convert_board_to_tensor(state),
-                    &move_ids,
-                );
-                distr_map.insert(state, distr);
-                distr_map.get(&state).unwrap()
+                let priors = self.evaluator.priors(&position, &move_ids);
+                self.saved_distr.insert(state, priors.clone());
+                priors
             }
         };
+        self.mcts_move_ids = move_ids;
+        // lower the minimum if there are too many moves to select every one often enough
+        let mcts_minimum = match self.config.validate_children(self.mcts_move_ids.len()) {
+            Ok(()) => self.config.mcts_minimum,
+            Err(_) => mcts_num / self.mcts_move_ids.len(),
+        };
+        if greedy {
+            self.assign_greedy(mcts_num, mcts_minimum);
+            return;
+        }
+        let mut weights = self.mcts_priors.clone();
+        let epsilon = self.config.move_selection.dirichlet_epsilon;
+        // noise needs at least two moves to make a difference
+        if epsilon > 0.0 && weights.len() > 1 {
+            let dirichlet = Dirichlet::new_with_size(self.config.move_selection.dirichlet_alpha, weights.len())
+                .expect("Dirichlet noise requires a positive alpha");
+            for (weight, noise) in weights.iter_mut().zip(dirichlet.sample(&mut self.rng)) {
+                *weight = (1.0 - epsilon) * *weight + epsilon * noise;
+            }
+        }
+        let distr = WeightedIndex::new(&weights).unwrap();
         // use distribution to draw next moves
-        for _ in 0..self.mcts_num {
-            let count = self
-                .mcts_board_ids
-                .get_mut(&distr.sample(&mut rng))
-                .unwrap();
-            *count += 1;
+        for _ in 0..mcts_num {
+            self.mcts_board_ids[distr.sample(&mut self.rng)] += 1;
         }
 
         // redistribute chosen next state ids to match minimum count
-        board_ids.shuffle(&mut rng);
-        for idx in &board_ids {
-            let mut count = *self.mcts_board_ids.get_mut(idx).unwrap();
-            if count < self.mcts_minimum {
-                self.mcts_board_ids.insert(*idx, 0);
+        board_ids.shuffle(&mut self.rng);
+        for idx in board_ids {
+            let mut count = self.mcts_board_ids[idx];
+            if count < mcts_minimum {
+                self.mcts_board_ids[idx] = 0;
                 while count > 0 {
-                    let other_idx = distr.sample(&mut rng);
-                    let other_count = self.mcts_board_ids.get_mut(&other_idx).unwrap();
-                    if *other_count >= self.mcts_minimum - 1 {
+                    let other_idx = distr.sample(&mut self.rng);
+                    let other_count = &mut self.mcts_board_ids[other_idx];
+                    if *other_count >= mcts_minimum - 1 {
                         *other_count += 1;
                         count -= 1;
                     }
@@ -293,149 +1324,273 @@ impl MagisterLudi {
         }
     }
 
-    // pushes the chosen moves to the queue for simulation
-    fn push_to_queue(&mut self) {
-        let game_queue = self.game_queue.clone();
-        self.mcts_results.lock().unwrap().clear();
-        self.mcts_counts.clear();
-        let mut queue = game_queue.lock().unwrap();
-        // first push every position once to make greater use of saved distributions
-        for (idx, count) in self.mcts_board_ids.iter() {
+    // assigns the number of simulations in proportion to the move distribution without randomness
+    fn assign_greedy(&mut self, mcts_num: usize, mcts_minimum: usize) {
+        let mut remainders: Vec<(usize, f32)> = Vec::with_capacity(self.mcts_priors.len());
+        let mut assigned: usize = 0;
+        for (idx, prior) in self.mcts_priors.iter().enumerate() {
+            let share = prior * mcts_num as f32;
+            let count = share.floor() as usize;
+            self.mcts_board_ids[idx] = count;
+            remainders.push((idx, share - count as f32));
+            assigned += count;
+        }
+        // the largest remainders receive the simulations which are left
+        remainders.sort_by(|a, b| b.1.total_cmp(&a.1));
+        for (idx, _) in remainders.iter().cycle().take(mcts_num.saturating_sub(assigned)) {
+            self.mcts_board_ids[*idx] += 1;
+        }
+        // positions below the minimum count give their simulations to the most probable position
+        let best_idx = (0..self.mcts_priors.len())
+            .max_by(|a, b| self.mcts_priors[*a].total_cmp(&self.mcts_priors[*b]).then(b.cmp(a)))
+            .unwrap();
+        for idx in 0..self.mcts_priors.len() {
+            let count = self.mcts_board_ids[idx];
+            if idx != best_idx && count < mcts_minimum {
+                self.mcts_board_ids[idx] = 0;
+                self.mcts_board_ids[best_idx] += count;
+            }
+        }
+    }
+
+    // chooses the index of the move to play from the ranked search results
+    fn select_child(&mut self, report: &SearchReport, temperature: f32) -> usize {
+        if temperature <= 0.0 || report.children.len() == 1 {
+            return 0;
+        }
+        // children are sorted, so the first one has the highest value
+        let best_value = report.children[0].mean_value;
+        let weights: Vec<f32> = report
+            .children
+            .iter()
+            .map(|child| ((child.mean_value - best_value) / temperature).exp())
+            .collect();
+        WeightedIndex::new(weights).unwrap().sample(&mut self.rng)
+    }
+
+    // sends the chosen moves to the MCTS threads for simulation
+    fn push_to_queue(&mut self, root: &AbaloneGame, previous: &[Board]) {
+        self.mcts_results.clear();
+        let root_black_tomove = root.get_black_tomove();
+        let recent: Vec<Board> = std::iter::once(root.get_state())
+            .chain(previous.iter().copied())
+            .take(self.evaluator.history_len())
+            .collect();
+        // first send every position once to make greater use of saved distributions
+        for (idx, count) in self.mcts_board_ids.iter().enumerate() {
             if *count > 0 {
-                let board = self.abalone.get_next_position(*idx);
-                queue.push((self.abalone.mcts_copy(), board));
+                self.send_job(root, idx, root_black_tomove, &recent);
             }
         }
-        for (idx, count) in self.mcts_board_ids.iter() {
+        for (idx, count) in self.mcts_board_ids.iter().enumerate() {
             if *count > 0 {
-                let board = self.abalone.get_next_position(*idx);
                 for _ in 0..(count - 1) {
-                    queue.push((self.abalone.mcts_copy(), board));
+                    self.send_job(root, idx, root_black_tomove, &recent);
                 }
-                self.mcts_counts.insert(board, *count as f32);
             }
         }
     }
 
-    // chooses the next move depending on the outcome of the MCTS
-    fn choose_next_move(&mut self) -> Board {
-        // await queue results
-        let mcts_finished = self.mcts_finished.clone();
-        *mcts_finished.lock().unwrap() = 0;
+    // hands a single child state of the root over to the MCTS threads
+    fn send_job(&self, root: &AbaloneGame, child: usize, root_black_tomove: bool, recent: &[Board]) {
+        let job = LeafJob {
+            game: root.mcts_copy(),
+            child,
+            board: root.get_next_position(child),
+            root_black_tomove,
+            recent: recent.to_vec(),
+        };
+        // the agent holds a receiver itself, so the channel cannot be disconnected
+        self.job_sender.send(job).unwrap();
+    }
 
-        let sleep_time = time::Duration::from_millis(100);
-        while *mcts_finished.lock().unwrap() < self.mcts_num {
-            thread::sleep(sleep_time);
+    // awaits the outcome of the MCTS and ranks the follow-up positions
+    fn collect_results(&mut self, root: &AbaloneGame, mcts_num: usize, search_start: Instant) -> SearchReport {
+        // await the results of all leafs, a result is received as soon as a thread finished it
+        let check_interval = time::Duration::from_millis(self.config.thread_check_millis);
+        let mut received: usize = 0;
+        let mut nodes: usize = 0;
+        while received < mcts_num {
+            match self.result_receiver.recv_timeout(check_interval) {
+                Ok(leaf) => {
+                    let results = self.mcts_results.entry(leaf.child).or_default();
+                    // the first simulation always provides the best line
+                    if results.visits == 0 || leaf.value > results.best_value {
+                        results.best_value = leaf.value;
+                        results.best_line = leaf.line;
+                    }
+                    results.value_sum += leaf.value;
+                    results.visits += 1;
+                    nodes += leaf.nodes;
+                    received += 1;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    // only happens if a simulation takes very long, so be sure the threads are still alive
+                    if !self.check_threads_all_active() {
+                        panic!("MCTS threads stopped while the search was running")
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => unreachable!(),
+            }
         }
 
-        let mcts_results = self.mcts_results.clone();
-        let mut best_state = game::BELGIAN_DAISY;
-        let mut best_result: f32 = -(self.mcts_num as f32); // worst result possible
-        for (state, result_value) in mcts_results.lock().unwrap().iter_mut() {
-            // the result value is averaged
-            *result_value /= self.mcts_counts.get(state).unwrap();
-            // this variant is for sure not the best, but it is also not slowing down the overall process
-            if *result_value > best_result {
-                best_result = *result_value;
-                best_state = *state;
-            }
+        self.create_report(root, nodes, search_start.elapsed())
+    }
+
+    // summarizes the received simulation results
+    fn create_report(&mut self, root: &AbaloneGame, nodes: usize, elapsed: Duration) -> SearchReport {
+        let mut child_ids: Vec<usize> = self.mcts_results.keys().copied().collect();
+        child_ids.sort_unstable();
+        let mut children: Vec<ChildReport> = child_ids
+            .into_iter()
+            .map(|idx| {
+                let results = self.mcts_results.remove(&idx).unwrap();
+                ChildReport {
+                    board: root.get_next_position(idx),
+                    move_id: self.mcts_move_ids[idx],
+                    visits: results.visits,
+                    // the result value is averaged
+                    mean_value: results.value_sum / results.visits as f32,
+                    prior: self.mcts_priors[idx],
+                    line: results.best_line,
+                }
+            })
+            .collect();
+        // stable sort, so equal values keep the order of the move generation
+        children.sort_by(|a, b| b.mean_value.total_cmp(&a.mean_value));
+        let best_line = children[0].line.clone();
+        let nodes_per_second = if elapsed.is_zero() {
+            0.0
+        } else {
+            nodes as f64 / elapsed.as_secs_f64()
+        };
+        SearchReport {
+            children,
+            best_line,
+            nodes,
+            elapsed,
+            nodes_per_second,
         }
-        self.abalone.update_state(best_state);
-        best_state
     }
 
     // starts the threads for the MCTS when the class is initialized
-    fn start_threads(&mut self, model_path: &str) {
-        for i in 0..self.mcts_parallel {
+    fn start_threads(&mut self) {
+        for i in 0..self.config.mcts_parallel {
             let t_receiver = self.stop_receiver.clone();
-            let game_queue = self.game_queue.clone();
-            let mcts_results = self.mcts_results.clone();
-            let mcts_finished = self.mcts_finished.clone();
+            let job_receiver = self.job_receiver.clone();
+            let result_sender = self.result_sender.clone();
             let saved_distr = self.saved_distr.clone();
-            let mcts_depth = self.mcts_depth;
-            let main_black_tomove = self.abalone.get_black_tomove();
-            let thread_path = model_path.to_string();
+            let mcts_depth = self.config.mcts_depth;
+            let evaluator = self.evaluator.clone();
+            let thread_seed = self.config.seed.map(|seed| Self::thread_seed(seed, i));
 
             let handle = thread::spawn(move || {
-                let sleep_time = time::Duration::from_millis(500);
-                // create model for each thread
-                let (session, _graph, inp, distr_out, rating_out) = Self::create_session(&thread_path);
-                let mut rng = thread_rng();
+                let mut rng = match thread_seed {
+                    Some(seed) => StdRng::seed_from_u64(seed),
+                    None => StdRng::from_entropy(),
+                };
 
                 loop {
-                    if let Ok(signal) = t_receiver.try_recv() {
-                        if signal {
-                            println!("Thread {i} terminating");
-                            break;
-                        }
-                    }
-
-                    let leaf_entry = game_queue.lock().unwrap().pop();
-                    match leaf_entry {
-                        Some(entry) => {
-                            let mut moves_performed: usize = 0;
-                            let (mut aba, next_board) = entry;
-                            aba.update_state(next_board);
-                            while !aba.get_game_ended() {
-                                let (state, move_ids) = aba.calc_reasonalbe_moves();
-                                let index_opt = saved_distr
-                                    .lock()
-                                    .unwrap()
-                                    .get(&state)
-                                    .map(|distr| distr.sample(&mut rng));
-                                let brd_index = match index_opt {
-                                    Some(idx) => idx,
-                                    None => {
-                                        let distr = Self::calc_distribution(
-                                            &session,
-                                            &inp,
-                                            &distr_out,
-                                            &Self::convert_board_to_tensor(state),
-                                            &move_ids,
-                                        );
-                                        let idx = distr.sample(&mut rng);
-                                        saved_distr.lock().unwrap().insert(state, distr);
-                                        idx
-                                    }
-                                };
-                                let next_state = aba.get_next_position(brd_index);
-                                aba.update_state(next_state);
-                                moves_performed += 1;
-                                // will never be true for self.mcts_depth == 0
-                                if moves_performed == mcts_depth || aba.get_game_ended() {
-                                    break;
-                                }
-                            }
-                            let black_factor_main: f32 = if main_black_tomove { -1.0 } else { 1.0 };
-                            let mut leaf_result: f32 = if aba.get_game_ended() {
-                                aba.get_game_result().into()
-                            } else {
-                                let black_factor_leaf: f32 =
-                                    if aba.get_black_tomove() { -1.0 } else { 1.0 };
-                                let state = aba.get_rotated_state();
-                                Self::calc_rating(
-                                    &session,
-                                    &inp,
-                                    &rating_out,
-                                    &Self::convert_board_to_tensor(state),
-                                ) * black_factor_leaf
-                            };
-                            leaf_result *= black_factor_main;
-                            mcts_results.lock().unwrap().insert(next_board, leaf_result);
-                            *mcts_finished.lock().unwrap() += 1;
-                        }
-                        None => {
-                            thread::sleep(sleep_time);
-                        }
+                    // blocks until either a stop signal or a new position arrives
+                    let job = select! {
+                        recv(t_receiver) -> signal => match signal {
+                            Ok(false) => continue,
+                            // a disconnected channel means the agent was dropped
+                            _ => break,
+                        },
+                        recv(job_receiver) -> job => match job {
+                            Ok(job) => job,
+                            // the agent was dropped
+                            Err(_) => break,
+                        },
                     };
+                    let leaf = Self::simulate_leaf(&*evaluator, &saved_distr, mcts_depth, &mut rng, job);
+                    // the agent holds a receiver itself, so the channel cannot be disconnected
+                    result_sender.send(leaf).unwrap();
                 }
             });
             self.mcts_handles.push(handle);
         }
     }
 
+    // derives the seed for the random number generator of a MCTS thread
+    fn thread_seed(seed: u64, thread_idx: usize) -> u64 {
+        // the main thread uses the seed itself, so every thread is shifted by an odd constant
+        seed ^ (thread_idx as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    // plays the game from a leaf position until the maximum depth or the game end and evaluates the outcome
+    fn simulate_leaf(
+        evaluator: &M,
+        saved_distr: &DistributionCache,
+        mcts_depth: usize,
+        rng: &mut StdRng,
+        job: LeafJob,
+    ) -> LeafResult {
+        let LeafJob { game: mut aba, child, board: next_board, root_black_tomove, recent } = job;
+        let history_len = evaluator.history_len();
+        let mut moves_performed: usize = 0;
+        aba.update_state(next_board);
+        let mut line = vec![aba.get_state()];
+        while !aba.get_game_ended() {
+            let (state, move_ids) = aba.calc_reasonalbe_moves();
+            let index_opt = saved_distr.get_with(&state, |distr| distr.sample(rng));
+            let brd_index = match index_opt {
+                Some(idx) => idx,
+                None => {
+                    // the cache is keyed by the board alone, so with a history the distribution
+                    // of the first visit is reused for the same board reached in another way
+                    let position = Self::leaf_position(&aba, &line, &recent, history_len);
+                    let priors = evaluator.priors(&position, &move_ids);
+                    let idx = WeightedIndex::new(&priors).unwrap().sample(rng);
+                    saved_distr.insert(state, priors);
+                    idx
+                }
+            };
+            let next_state = aba.get_next_position(brd_index);
+            aba.update_state(next_state);
+            line.push(aba.get_state());
+            moves_performed += 1;
+            // will never be true for self.mcts_depth == 0
+            if moves_performed == mcts_depth || aba.get_game_ended() {
+                break;
+            }
+        }
+        let black_factor_main: f32 = if root_black_tomove { -1.0 } else { 1.0 };
+        let mut leaf_result: f32 = if aba.get_game_ended() {
+            aba.get_game_result().into()
+        } else {
+            let black_factor_leaf: f32 =
+                if aba.get_black_tomove() { -1.0 } else { 1.0 };
+            evaluator.value(&Self::leaf_position(&aba, &line, &recent, history_len)) * black_factor_leaf
+        };
+        leaf_result *= black_factor_main;
+        LeafResult {
+            child,
+            value: leaf_result,
+            nodes: line.len(),
+            line,
+        }
+    }
+
+    // builds the position of a simulated game, line holds the states since the root with the
+    // current state last and recent the root state and the states before it, the most recent first
+    fn leaf_position(aba: &AbaloneGame, line: &[Board], recent: &[Board], history_len: usize) -> Position {
+        let previous: Vec<Board> = line
+            .iter()
+            .rev()
+            .skip(1)
+            .chain(recent.iter())
+            .take(history_len)
+            .copied()
+            .collect();
+        Position::from_game(aba, &previous, history_len)
+    }
+
     /// checks whether all daemon threads for the MCTS are still running
     /// 
+    /// for an agent using a search pool these are the threads of the pool
+    /// 
     /// # Returns
     /// 
     /// * `all_active` true if all daemon MCTS threads are still running, else false
@@ -443,138 +1598,76 @@ impl MagisterLudi {
     /// # Examples
     /// 
     /// ```rust
-    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::new(rustai_abalone::game::BELGIAN_DAISY, None, 10, 5, 1, 5);
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
     /// let all_active = magister_ludi.check_threads_all_active();
     /// # magister_ludi.stop_execution();
     /// ```
     pub fn check_threads_all_active(& self) -> bool {
+        if let Some(search_pool) = &self.search_pool {
+            return search_pool.check_threads_all_active();
+        }
         let mut num_active: usize = 0;
         for handle in self.mcts_handles.as_slice() {
             if !handle.is_finished() {
                 num_active += 1;
             }
         }
-        if num_active == self.mcts_parallel {
+        if num_active == self.config.mcts_parallel {
             return true;
         }
         false
     }
 
-    // creates a tensorflow session and input and output operations for the model
-    fn create_session(model_path: &str) -> (Session, Graph, Operation, Operation, Operation) {
-        let signature_input_parameter_name = "input_8"; // adjust
-        let signature_output_distr_name = "pol_prediction"; // adjust
-        let signature_output_rating_name = "val_prediction"; // adjust
-
-        // Initialize save_dir, input tensor, and an empty graph
-        // let save_dir = "C:\\Users\\hlocke\\Documents\\Repos\\UdemyRust\\MyTasks\\tf_ludi\\src\\magister_zero_unwrap_save";
-
-        let mut graph = Graph::new();
-
-        // Load saved model bundle (session state + meta_graph data)
-        let bundle =
-            SavedModelBundle::load(&SessionOptions::new(), ["serve"], &mut graph, model_path)
-                .expect("Can't load saved model");
-
-        // Get signature metadata from the model bundle
-        let signature = bundle
-            .meta_graph_def()
-            .get_signature("serving_default")
-            .unwrap();
-
-        // Get input/output info
-        let input_info = signature.get_input(signature_input_parameter_name).unwrap();
-        let output_distr_info = signature.get_output(signature_output_distr_name).unwrap();
-        let output_rating_info = signature.get_output(signature_output_rating_name).unwrap();
-
-        // Get input/output ops from graph
-        let input_op = graph
-            .operation_by_name_required(&input_info.name().name)
-            .unwrap();
-        let output_distr_op = graph
-            .operation_by_name_required(&output_distr_info.name().name)
-            .unwrap();
-        let output_rating_op = graph
-            .operation_by_name_required(&output_rating_info.name().name)
-            .unwrap();
-
-        // Get the session from the loaded model bundle
-        let session = bundle.session;
-        (session, graph, input_op, output_distr_op, output_rating_op)
-    }
-
-    // prepares the current board for tensorflow input
-    fn convert_board_to_tensor(board: game::Board) -> Tensor<f32> {
-        let mut tensor: Tensor<f32> =
-            Tensor::new(&[1, game::BOARD_SIZE as u64, game::BOARD_SIZE as u64, 4]);
-        for x in 0..game::BOARD_SIZE {
-            for y in 0..game::BOARD_SIZE {
-                tensor.set(&[0, x as u64, y as u64, board[x][y] as u64], 1.0);
-            }
+    // checks whether the inner game representation of the agent ended by win, loss or draw
+    fn check_game_ended(&mut self) {
+        let game_ended = self.abalone.get_game_ended();
+        if game_ended {
+            self.stop_threads();
         }
-        tensor
     }
 
-    // calcuates the action/move distribution for a given position
-    fn calc_distribution(
-        session: &Session,
-        input: &Operation,
-        distr_output: &Operation,
-        tensor: &Tensor<f32>,
-        move_ids: &[usize],
-    ) -> WeightedIndex<f32> {
-        let mut args = SessionRunArgs::new();
-        args.add_feed(input, 0, tensor); // Add any inputs
-
-        let out = args.request_fetch(distr_output, 0); // Request outputs
-
-        // Run model
-        session
-            .run(&mut args) // Pass to session to run
-            .expect("Error occurred during calculations");
-
-        // Fetch outputs after graph execution
-        let move_logits: Tensor<f32> = args.fetch(out).unwrap();
-
-        // use output to calculate distribution by softmax
-        let move_exp: Vec<f32> = move_ids.iter().map(|idx| move_logits[*idx].exp()).collect();
-        let move_exp_sum: f32 = move_exp.iter().sum();
-        let weights: Vec<f32> = move_exp.iter().map(|val| val / move_exp_sum).collect();
-        WeightedIndex::new(weights).unwrap()
+    // stops all MCTS threads and waits for them to finish
+    fn stop_threads(&mut self) {
+        for _ in 0..self.mcts_handles.len() {
+            self.stop_sender.send(true).unwrap();
+        }
+        while let Some(handle) = self.mcts_handles.pop() {
+            handle.join().unwrap();
+        }
     }
 
-    // calculates the evaluation for given position
-    fn calc_rating(
-        session: &Session,
-        input: &Operation,
-        rating_output: &Operation,
-        tensor: &Tensor<f32>,
-    ) -> f32 {
-        let mut args = SessionRunArgs::new();
-        args.add_feed(input, 0, tensor); // Add any inputs
-
-        let out = args.request_fetch(rating_output, 1); // Request output
-
-        // Run model
-        session
-            .run(&mut args) // Pass to session to run
-            .expect("Error occurred during calculations");
-
-        // Fetch outputs after graph execution
-        args.fetch(out).unwrap()[0]
+    /// returns the hit and miss statistics of the cache for move distributions
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// let stats = magister_ludi.cache_stats();
+    /// println!("cached positions: {}, hit rate: {}", stats.entries, stats.hit_rate());
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn cache_stats(&self) -> CacheStats {
+        self.saved_distr.stats()
     }
 
-    // checks whether the inner game representation of the agent ended by win, loss or draw
-    fn check_game_ended(&mut self) {
-        let game_ended = self.abalone.get_game_ended();
-        if game_ended {
-            for _ in 0..self.mcts_parallel {
-                self.stop_sender.send(true).unwrap();
-            }
-            while let Some(handle) = self.mcts_handles.pop() {
-                handle.join().unwrap();
-            }
-        }
+    /// changes the maximum memory the cache for move distributions may occupy
+    ///
+    /// the new cap also applies to the running MCTS threads
+    ///
+    /// # Arguments
+    ///
+    /// * `memory_cap` - maximum memory in bytes, defaults to `cache::DEFAULT_CACHE_MEMORY`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
+    /// magister_ludi.set_cache_memory_cap(64 * 1024 * 1024);
+    /// # magister_ludi.stop_execution();
+    /// ```
+    pub fn set_cache_memory_cap(&mut self, memory_cap: usize) {
+        self.config.cache_memory = memory_cap;
+        self.saved_distr.set_memory_cap(memory_cap);
     }
 
     /// sets the inner game representation result to a draw and stops the daemon threads
@@ -582,7 +1675,7 @@ impl MagisterLudi {
     /// # Examples
     ///
     /// ```rust
-    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::new(rustai_abalone::game::BELGIAN_DAISY, None, 10, 5, 1, 5);
+    /// # let mut magister_ludi = rustai_abalone::player::MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, rustai_abalone::config::AgentConfig::builder().mcts_num(10).mcts_parallel(5).mcts_depth(5).build().unwrap(), std::sync::Arc::new(rustai_abalone::evaluator::HeuristicEvaluator::default()));
     /// magister_ludi.stop_execution();
     /// ```
     pub fn stop_execution(&mut self) {
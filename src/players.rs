@@ -0,0 +1,564 @@
+use rand::prelude::{Rng, SeedableRng, StdRng};
+use std::io::{self, BufRead, Write};
+
+use super::evaluator::{Evaluator, HeuristicEvaluator, Position};
+use super::game::{AbaloneGame, Board, BELGIAN_DAISY, BOARD_SIZE};
+use super::marble_moves::MOVES_IDX;
+use super::player::{AgentAction, MagisterLudi};
+use super::ratings::GameResult;
+
+// highest index of the board array, mirrors a coordinate for the point of view of black
+const BOARD_MAXID: usize = BOARD_SIZE - 1;
+
+/// participant of a game of Abalone, e.g. an agent, a simple bot or a human
+///
+/// all positions are exchanged as they are on the board (not rotated) and black moves first.
+/// A `Match` calls `new_game` for both players, then alternately `choose_move` for the side to move
+/// and `opponent_move` for the other side, and finally `game_over`.
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::game::{AbaloneGame, BELGIAN_DAISY};
+/// use rustai_abalone::player::AgentAction;
+/// use rustai_abalone::players::{Player, RandomPlayer};
+/// let mut player = RandomPlayer::new(Some(7));
+/// player.new_game(BELGIAN_DAISY);
+/// let AgentAction::Move(state) = player.choose_move() else { unreachable!() };
+/// assert!(AbaloneGame::validate_board(state));
+/// ```
+pub trait Player {
+    /// returns the name of the player, e.g. for a results file of `ratings`
+    fn name(&self) -> String;
+
+    /// starts a new game
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - starting position, black moves first
+    fn new_game(&mut self, board: Board);
+
+    /// receives the move of the opponent
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - position after the move of the opponent
+    fn opponent_move(&mut self, state: Board);
+
+    /// chooses the move for the current position
+    ///
+    /// # Returns
+    ///
+    /// * `action` - the position after the move, possibly with a draw offer, or the resignation
+    fn choose_move(&mut self) -> AgentAction;
+
+    /// decides whether the player accepts the draw offered with the last move of the opponent,
+    /// the default declines every offer
+    fn accept_draw(&mut self) -> bool {
+        false
+    }
+
+    /// is called when the game has ended
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - 1 if white won, -1 if black won and 0 for a draw
+    fn game_over(&mut self, _result: i8) {}
+}
+
+impl<P: Player + ?Sized> Player for Box<P> {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn new_game(&mut self, board: Board) {
+        (**self).new_game(board)
+    }
+
+    fn opponent_move(&mut self, state: Board) {
+        (**self).opponent_move(state)
+    }
+
+    fn choose_move(&mut self) -> AgentAction {
+        (**self).choose_move()
+    }
+
+    fn accept_draw(&mut self) -> bool {
+        (**self).accept_draw()
+    }
+
+    fn game_over(&mut self, result: i8) {
+        (**self).game_over(result)
+    }
+}
+
+impl<M: Evaluator + ?Sized + 'static> Player for MagisterLudi<M> {
+    fn name(&self) -> String {
+        "MagisterLudi".to_string()
+    }
+
+    fn new_game(&mut self, board: Board) {
+        self.start_new_game(board);
+    }
+
+    fn opponent_move(&mut self, state: Board) {
+        self.external_move(state, false);
+    }
+
+    /// searches the position, see `MagisterLudi::own_move`
+    fn choose_move(&mut self) -> AgentAction {
+        self.own_move(false)
+    }
+
+    /// searches the position, see `MagisterLudi::consider_draw_offer`
+    fn accept_draw(&mut self) -> bool {
+        self.consider_draw_offer()
+    }
+}
+
+// returns the position from the point of view of the side to move
+fn pov_board(state: &Board, black_tomove: bool) -> Board {
+    if black_tomove {
+        AbaloneGame::rotate_board(*state)
+    } else {
+        *state
+    }
+}
+
+// plays the follow-up position with the given index and returns the new state as it is on the board
+fn play_index(game: &mut AbaloneGame, idx: usize) -> AgentAction {
+    game.update_by_id(idx);
+    AgentAction::Move(game.get_state())
+}
+
+/// player that chooses every move uniformly at random, e.g. as the weakest opponent of a ladder
+pub struct RandomPlayer {
+    game: AbaloneGame,
+    rng: StdRng,
+}
+
+impl RandomPlayer {
+    /// creates a random player
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - seed for the random moves, if None it is seeded from system entropy
+    pub fn new(seed: Option<u64>) -> Self {
+        Self {
+            game: AbaloneGame::new(BELGIAN_DAISY),
+            rng: seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64),
+        }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn name(&self) -> String {
+        "RandomPlayer".to_string()
+    }
+
+    fn new_game(&mut self, board: Board) {
+        self.game = AbaloneGame::new(board);
+    }
+
+    fn opponent_move(&mut self, state: Board) {
+        self.game.update_state(pov_board(&state, self.game.get_black_tomove()));
+    }
+
+    fn choose_move(&mut self) -> AgentAction {
+        let (_, move_ids) = self.game.calc_reasonalbe_moves();
+        let idx = self.rng.gen_range(0..move_ids.len());
+        play_index(&mut self.game, idx)
+    }
+}
+
+/// player that looks one move ahead and plays the move with the best evaluation
+///
+/// ties are broken at random. The evaluator only sees the position after the move,
+/// so evaluators that need previous boards get none.
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::game::BELGIAN_DAISY;
+/// use rustai_abalone::players::{GreedyPlayer, Match, RandomPlayer};
+/// let mut game_match = Match::new(GreedyPlayer::heuristic(Some(1)), RandomPlayer::new(Some(2)));
+/// let result = game_match.play(BELGIAN_DAISY, 20);
+/// assert_eq!(result.history.len(), result.num_moves + 1);
+/// ```
+pub struct GreedyPlayer<E: Evaluator = HeuristicEvaluator> {
+    game: AbaloneGame,
+    evaluator: E,
+    rng: StdRng,
+}
+
+impl GreedyPlayer {
+    /// creates a greedy player with the default `evaluator::HeuristicEvaluator`
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - seed for breaking ties, if None it is seeded from system entropy
+    pub fn heuristic(seed: Option<u64>) -> Self {
+        Self::new(HeuristicEvaluator::default(), seed)
+    }
+}
+
+impl<E: Evaluator> GreedyPlayer<E> {
+    /// creates a greedy player
+    ///
+    /// # Arguments
+    ///
+    /// * `evaluator` - evaluates the positions after every possible move
+    /// * `seed` - seed for breaking ties, if None it is seeded from system entropy
+    pub fn new(evaluator: E, seed: Option<u64>) -> Self {
+        Self {
+            game: AbaloneGame::new(BELGIAN_DAISY),
+            evaluator,
+            rng: seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64),
+        }
+    }
+}
+
+impl<E: Evaluator> Player for GreedyPlayer<E> {
+    fn name(&self) -> String {
+        "GreedyPlayer".to_string()
+    }
+
+    fn new_game(&mut self, board: Board) {
+        self.game = AbaloneGame::new(board);
+    }
+
+    fn opponent_move(&mut self, state: Board) {
+        self.game.update_state(pov_board(&state, self.game.get_black_tomove()));
+    }
+
+    fn choose_move(&mut self) -> AgentAction {
+        let (_, move_ids) = self.game.calc_reasonalbe_moves();
+        // the follow-up positions are evaluated for the opponent, who is to move in them
+        let values: Vec<f32> = (0..move_ids.len())
+            .map(|idx| {
+                let opponent_view = AbaloneGame::rotate_board(self.game.get_next_position(idx));
+                -self.evaluator.value(&Position::from_board(opponent_view))
+            })
+            .collect();
+        let best_value = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let best: Vec<usize> = (0..values.len()).filter(|idx| values[*idx] >= best_value).collect();
+        let idx = best[self.rng.gen_range(0..best.len())];
+        play_index(&mut self.game, idx)
+    }
+}
+
+/// player that reads its moves from a text input and shows the board on a text output
+///
+/// a move is entered as the fields of the marbles to move followed by the field the first marble
+/// moves to, every field written as `<row><column>` with the indices of the board array, e.g.
+/// `73 74 63` moves the marbles on (7, 3) and (7, 4) to (6, 3) and (6, 4). Entering `resign`
+/// resigns the game. A player reading from the terminal is created with `HumanPlayer::stdin`.
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::game::{AbaloneGame, BELGIAN_DAISY};
+/// use rustai_abalone::player::AgentAction;
+/// use rustai_abalone::players::{HumanPlayer, Player};
+/// // black starts with the marbles in the lower left, the invalid move is asked again
+/// let input = "12 13\n72 73 62\n".as_bytes();
+/// let mut human = HumanPlayer::new("alice", input, Vec::new());
+/// human.new_game(BELGIAN_DAISY);
+/// let AgentAction::Move(state) = human.choose_move() else { unreachable!() };
+/// assert_eq!((state[6][2], state[6][3], state[7][2], state[7][3]), (2, 2, 0, 0));
+/// assert!(String::from_utf8_lossy(human.output()).contains("invalid move"));
+/// ```
+pub struct HumanPlayer<R: BufRead, W: Write> {
+    name: String,
+    game: AbaloneGame,
+    input: R,
+    output: W,
+}
+
+impl HumanPlayer<io::StdinLock<'static>, io::Stdout> {
+    /// creates a human player that plays on the terminal
+    pub fn stdin(name: &str) -> Self {
+        Self::new(name, io::stdin().lock(), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> HumanPlayer<R, W> {
+    /// creates a human player
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the player
+    /// * `input` - source of the entered moves
+    /// * `output` - target for the board and the prompts
+    pub fn new(name: &str, input: R, output: W) -> Self {
+        Self {
+            name: name.to_string(),
+            game: AbaloneGame::new(BELGIAN_DAISY),
+            input,
+            output,
+        }
+    }
+
+    /// returns the output the board and the prompts are written to
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    // writes the board with the row numbers and the columns of the first and last field of every row
+    fn show_board(&mut self) -> io::Result<()> {
+        let state = self.game.get_state();
+        writeln!(self.output)?;
+        for (row, fields) in state.iter().enumerate().take(BOARD_MAXID).skip(1) {
+            let columns: Vec<usize> = (1..BOARD_MAXID).filter(|col| fields[*col] != 3).collect();
+            let marbles: Vec<&str> = columns
+                .iter()
+                .map(|col| match fields[*col] {
+                    1 => "W",
+                    2 => "B",
+                    _ => ".",
+                })
+                .collect();
+            let indent = " ".repeat(row.abs_diff(BOARD_SIZE / 2));
+            writeln!(
+                self.output,
+                "{row} ({}-{}): {indent}{}",
+                columns[0],
+                columns[columns.len() - 1],
+                marbles.join(" ")
+            )?;
+        }
+        Ok(())
+    }
+
+    // reads a line from the input, None at the end of the input
+    fn read_line(&mut self) -> Option<String> {
+        let mut line = String::new();
+        match self.input.read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim().to_string()),
+        }
+    }
+
+    // converts an entered move into the ID of the move from the point of view of the side to move
+    fn parse_move(&self, line: &str) -> Option<usize> {
+        let mut fields = Vec::new();
+        for token in line.split_whitespace() {
+            let digits: Vec<usize> = token.chars().map(|c| c.to_digit(10).map(|digit| digit as usize)).collect::<Option<_>>()?;
+            let [row, col] = digits[..] else {
+                return None;
+            };
+            fields.push((row, col));
+        }
+        let (&target, marbles) = fields.split_last()?;
+        let first = *marbles.first()?;
+        let (mut dx, mut dy) = (target.0 as i8 - first.0 as i8, target.1 as i8 - first.1 as i8);
+        let black_tomove = self.game.get_black_tomove();
+        let mut key = String::new();
+        for (row, col) in marbles {
+            let (row, col) = if black_tomove { (BOARD_MAXID - row, BOARD_MAXID - col) } else { (*row, *col) };
+            key.push_str(&format!("{row}{col}"));
+        }
+        if black_tomove {
+            (dx, dy) = (-dx, -dy);
+        }
+        key.push_str(&format!("{dx}{dy}"));
+        MOVES_IDX.get(key.as_str()).copied()
+    }
+}
+
+impl<R: BufRead, W: Write> Player for HumanPlayer<R, W> {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn new_game(&mut self, board: Board) {
+        self.game = AbaloneGame::new(board);
+    }
+
+    fn opponent_move(&mut self, state: Board) {
+        self.game.update_state(pov_board(&state, self.game.get_black_tomove()));
+    }
+
+    /// asks for moves until a valid one is entered, the end of the input resigns the game
+    fn choose_move(&mut self) -> AgentAction {
+        let (_, move_ids) = self.game.calc_reasonalbe_moves();
+        let colour = if self.game.get_black_tomove() { "black (B)" } else { "white (W)" };
+        // the output is only informative, so failed writes are ignored
+        let _ = self.show_board();
+        loop {
+            let _ = write!(self.output, "{} plays {colour}, enter a move or `resign`: ", self.name);
+            let _ = self.output.flush();
+            let Some(line) = self.read_line() else {
+                return AgentAction::Resign;
+            };
+            if line == "resign" {
+                return AgentAction::Resign;
+            }
+            let idx = self
+                .parse_move(&line)
+                .and_then(|move_id| move_ids.iter().position(|id| *id == move_id));
+            match idx {
+                Some(idx) => return play_index(&mut self.game, idx),
+                None => {
+                    let _ = writeln!(self.output, "invalid move `{line}`");
+                }
+            }
+        }
+    }
+
+    fn accept_draw(&mut self) -> bool {
+        let _ = self.show_board();
+        let _ = write!(self.output, "{} is offered a draw, accept? [y/n] ", self.name);
+        let _ = self.output.flush();
+        matches!(self.read_line().as_deref(), Some("y" | "yes"))
+    }
+
+    fn game_over(&mut self, result: i8) {
+        let _ = self.show_board();
+        let message = match result {
+            1 => "white won",
+            -1 => "black won",
+            _ => "the game was drawn",
+        };
+        let _ = writeln!(self.output, "game over, {message}");
+    }
+}
+
+/// reason why a match ended
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Termination {
+    /// the game ended by the rules, by pushing off six marbles, repetition or moves without losses
+    Rules,
+    /// the side to move resigned
+    Resignation,
+    /// a draw offer was accepted
+    DrawAgreed,
+    /// the side to move returned a position that cannot be reached by a move, it lost the game
+    IllegalMove,
+    /// the maximum number of moves was reached, the game counts as draw
+    MoveLimit,
+}
+
+/// result of a match between two players
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchResult {
+    /// name of the player with the white marbles
+    pub white: String,
+    /// name of the player with the black marbles
+    pub black: String,
+    /// 1 if white won, -1 if black won and 0 for a draw
+    pub result: i8,
+    /// reason why the match ended
+    pub termination: Termination,
+    /// number of moves played
+    pub num_moves: usize,
+    /// states of the game as they are on the board, the starting position first
+    pub history: Vec<Board>,
+}
+
+impl MatchResult {
+    /// returns the result as an entry of a results file, see `ratings::GameResult`
+    pub fn game_result(&self) -> GameResult {
+        GameResult::new(&self.white, &self.black, self.result)
+    }
+}
+
+/// runs games between two players over an `AbaloneGame` that checks every move
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::game::BELGIAN_DAISY;
+/// use rustai_abalone::players::{Match, RandomPlayer, Termination};
+/// let mut game_match = Match::new(RandomPlayer::new(Some(1)), RandomPlayer::new(Some(2)));
+/// let result = game_match.play(BELGIAN_DAISY, 10);
+/// assert_eq!(result.termination, Termination::MoveLimit);
+/// assert_eq!(result.game_result().to_string(), "RandomPlayer RandomPlayer 0");
+/// ```
+pub struct Match<W: Player, B: Player> {
+    white: W,
+    black: B,
+}
+
+impl<W: Player, B: Player> Match<W, B> {
+    /// creates a match between the players
+    ///
+    /// # Arguments
+    ///
+    /// * `white` - player with the white marbles
+    /// * `black` - player with the black marbles, black moves first
+    pub fn new(white: W, black: B) -> Self {
+        Self { white, black }
+    }
+
+    /// returns the players, white first
+    pub fn into_players(self) -> (W, B) {
+        (self.white, self.black)
+    }
+
+    /// plays a game and notifies both players about its end
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - starting position, black moves first
+    /// * `max_moves` - the game counts as draw after this many moves, see `selfplay::DEFAULT_MAX_MOVES`
+    ///
+    /// # Panics
+    ///
+    /// will panic if the starting position is invalid
+    pub fn play(&mut self, board: Board, max_moves: usize) -> MatchResult {
+        let mut game = AbaloneGame::new(board);
+        self.white.new_game(board);
+        self.black.new_game(board);
+        let mut termination = Termination::MoveLimit;
+        while game.get_num_moves() < max_moves {
+            if game.get_game_ended() {
+                termination = Termination::Rules;
+                break;
+            }
+            let black_tomove = game.get_black_tomove();
+            // the side to move loses by resigning or moving illegally
+            let loss = if black_tomove { 1 } else { -1 };
+            let (mover, opponent): (&mut dyn Player, &mut dyn Player) = if black_tomove {
+                (&mut self.black, &mut self.white)
+            } else {
+                (&mut self.white, &mut self.black)
+            };
+            let action = mover.choose_move();
+            let Some(state) = action.board() else {
+                game.end_with_result(loss);
+                termination = Termination::Resignation;
+                break;
+            };
+            let (_, move_ids) = game.calc_reasonalbe_moves();
+            let next_pov = pov_board(&state, black_tomove);
+            let Some(idx) = (0..move_ids.len()).find(|idx| game.get_next_position(*idx) == next_pov) else {
+                game.end_with_result(loss);
+                termination = Termination::IllegalMove;
+                break;
+            };
+            game.update_by_id(idx);
+            opponent.opponent_move(state);
+            if matches!(action, AgentAction::MoveOfferingDraw(_)) && !game.get_game_ended() && opponent.accept_draw() {
+                game.end_with_result(0);
+                termination = Termination::DrawAgreed;
+                break;
+            }
+        }
+        if termination == Termination::MoveLimit && game.get_game_ended() {
+            // the last move ended the game
+            termination = Termination::Rules;
+        }
+        let result = if game.get_game_ended() { game.get_game_result() } else { 0 };
+        self.white.game_over(result);
+        self.black.game_over(result);
+        MatchResult {
+            white: self.white.name(),
+            black: self.black.name(),
+            result,
+            termination,
+            num_moves: game.get_num_moves(),
+            history: game.get_state_history().to_vec(),
+        }
+    }
+}
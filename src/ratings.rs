@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// rating of a player that plays evenly against every opponent
+pub const BASE_RATING: f64 = 1500.0;
+
+/// converts a difference of natural log-strengths into Elo points
+const ELO_PER_NATURAL: f64 = 400.0 / std::f64::consts::LN_10;
+
+/// the fit stops as soon as no strength changes by more than this relative amount
+const FIT_TOLERANCE: f64 = 1e-10;
+
+/// maximum number of iterations of the fit
+const MAX_ITERATIONS: usize = 10_000;
+
+/// error while reading or writing results and ratings
+#[derive(Debug)]
+pub enum RatingError {
+    /// a file could not be read or written
+    Io(io::Error),
+    /// a line of a results file is invalid, the line number starts at 1
+    Parse { line: usize, message: String },
+    /// a ratings file is not valid JSON
+    Json(String),
+}
+
+impl fmt::Display for RatingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access ratings: {err}"),
+            Self::Parse { line, message } => write!(f, "invalid result in line {line}: {message}"),
+            Self::Json(msg) => write!(f, "invalid ratings file: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for RatingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RatingError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// result of a single game between two players
+///
+/// in a results file every game is a line `<white> <black> <result>` with the result
+/// 1 if white won, -1 if black won and 0 for a draw, like `AbaloneGame::get_game_result`.
+/// Player names must not contain whitespace. Empty lines and lines starting with `#` are ignored.
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::ratings::GameResult;
+/// let results = GameResult::parse("# checkpoints\nmagister-2 magister-1 1\nalice magister-2 0\n").unwrap();
+/// assert_eq!(results[0], GameResult::new("magister-2", "magister-1", 1));
+/// assert_eq!(results[1].to_string(), "alice magister-2 0");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameResult {
+    /// name of the player with the white marbles
+    pub white: String,
+    /// name of the player with the black marbles
+    pub black: String,
+    /// 1 if white won, -1 if black won and 0 for a draw
+    pub result: i8,
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.white, self.black, self.result)
+    }
+}
+
+impl GameResult {
+    /// creates the result of a game
+    pub fn new(white: &str, black: &str, result: i8) -> Self {
+        Self {
+            white: white.to_string(),
+            black: black.to_string(),
+            result,
+        }
+    }
+
+    /// parses the lines of a results file
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - content of the results file
+    pub fn parse(content: &str) -> Result<Vec<Self>, RatingError> {
+        let mut results = Vec::new();
+        for (idx, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |message: &str| RatingError::Parse {
+                line: idx + 1,
+                message: message.to_string(),
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [white, black, result] = fields[..] else {
+                return Err(parse_error("expected white, black and result"));
+            };
+            let result = match result {
+                "1" => 1,
+                "0" => 0,
+                "-1" => -1,
+                _ => return Err(parse_error("the result must be 1, 0 or -1")),
+            };
+            if white == black {
+                return Err(parse_error("a player cannot play against itself"));
+            }
+            results.push(Self::new(white, black, result));
+        }
+        Ok(results)
+    }
+
+    /// reads the results file at the given path
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, RatingError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// appends results to the results file at the given path, the file is created if necessary
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path of the results file
+    /// * `results` - results to append
+    pub fn append<P: AsRef<Path>>(path: P, results: &[Self]) -> Result<(), RatingError> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        for result in results {
+            writeln!(file, "{result}")?;
+        }
+        Ok(())
+    }
+}
+
+/// rating of a single player
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    /// name of the player
+    pub player: String,
+    /// rating on the Elo scale, see `BASE_RATING`
+    pub rating: f64,
+    /// standard error of the rating in Elo points
+    pub uncertainty: f64,
+    /// number of games of the player
+    pub games: usize,
+    /// average score of the player, a draw counts as half a win
+    pub score: f64,
+}
+
+/// options of the rating calculation
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RatingOptions {
+    /// number of virtual draws of every player against an opponent rated `BASE_RATING`,
+    /// keeps the ratings of players without losses or wins finite
+    pub prior_games: f64,
+}
+
+impl Default for RatingOptions {
+    fn default() -> Self {
+        Self { prior_games: 2.0 }
+    }
+}
+
+/// Bradley-Terry ratings of all players on the Elo scale, sorted from the best to the worst player
+///
+/// the ratings are the maximum likelihood estimate of the Bradley-Terry model, so a difference
+/// of 400 points means the better player scores 10 times as much as the other one. Draws count
+/// as half a win for both players. The uncertainty is the standard error of each rating when the
+/// ratings of the opponents are taken as exact.
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::ratings::{GameResult, RatingOptions, RatingTable};
+/// let results = GameResult::parse("new old 1\nold new -1\nnew old 0\n").unwrap();
+/// let table = RatingTable::from_results(&results, RatingOptions::default());
+/// assert_eq!(table.ratings[0].player, "new");
+/// assert!(table.get("new").unwrap().rating > table.get("old").unwrap().rating);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RatingTable {
+    /// ratings of all players
+    pub ratings: Vec<Rating>,
+}
+
+impl RatingTable {
+    /// computes the ratings of all players in the results
+    ///
+    /// # Arguments
+    ///
+    /// * `results` - games of the players
+    /// * `options` - strength of the prior
+    ///
+    /// # Panics
+    ///
+    /// will panic if `prior_games` is not positive
+    pub fn from_results(results: &[GameResult], options: RatingOptions) -> Self {
+        assert!(options.prior_games > 0.0, "the ratings need a positive number of prior games");
+        let mut indices: BTreeMap<&str, usize> = BTreeMap::new();
+        for result in results {
+            for player in [&result.white, &result.black] {
+                let next = indices.len();
+                indices.entry(player.as_str()).or_insert(next);
+            }
+        }
+        let num_players = indices.len();
+        // points of every player and the games of every pair
+        let mut points = vec![0.0; num_players];
+        let mut games = vec![0; num_players];
+        let mut pairs: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+        for result in results {
+            let (white, black) = (indices[result.white.as_str()], indices[result.black.as_str()]);
+            points[white] += (f64::from(result.result) + 1.0) / 2.0;
+            points[black] += (1.0 - f64::from(result.result)) / 2.0;
+            games[white] += 1;
+            games[black] += 1;
+            *pairs.entry((white.min(black), white.max(black))).or_insert(0.0) += 1.0;
+        }
+        let prior = options.prior_games;
+        // minorization-maximization of the likelihood, the virtual opponent keeps a strength of 1
+        let mut strengths = vec![1.0; num_players];
+        for _ in 0..MAX_ITERATIONS {
+            let mut denominators: Vec<f64> = strengths.iter().map(|strength| prior / (strength + 1.0)).collect();
+            for (&(first, second), count) in &pairs {
+                let share = count / (strengths[first] + strengths[second]);
+                denominators[first] += share;
+                denominators[second] += share;
+            }
+            let mut max_change: f64 = 0.0;
+            for idx in 0..num_players {
+                let updated = (points[idx] + prior / 2.0) / denominators[idx];
+                max_change = max_change.max((updated / strengths[idx] - 1.0).abs());
+                strengths[idx] = updated;
+            }
+            if max_change < FIT_TOLERANCE {
+                break;
+            }
+        }
+        // the fisher information of every log-strength gives its standard error
+        let mut information: Vec<f64> = strengths
+            .iter()
+            .map(|strength| prior * strength / (strength + 1.0).powi(2))
+            .collect();
+        for (&(first, second), count) in &pairs {
+            let shared = count * strengths[first] * strengths[second] / (strengths[first] + strengths[second]).powi(2);
+            information[first] += shared;
+            information[second] += shared;
+        }
+        let mut ratings: Vec<Rating> = indices
+            .into_iter()
+            .map(|(player, idx)| Rating {
+                player: player.to_string(),
+                rating: BASE_RATING + ELO_PER_NATURAL * strengths[idx].ln(),
+                uncertainty: ELO_PER_NATURAL / information[idx].sqrt(),
+                games: games[idx],
+                score: points[idx] / games[idx] as f64,
+            })
+            .collect();
+        ratings.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+        Self { ratings }
+    }
+
+    /// returns the rating of the player
+    pub fn get(&self, player: &str) -> Option<&Rating> {
+        self.ratings.iter().find(|rating| rating.player == player)
+    }
+
+    /// reads ratings that were saved with `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RatingError> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|err| RatingError::Json(err.to_string()))
+    }
+
+    /// saves the ratings as JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RatingError> {
+        let content = serde_json::to_string_pretty(self).map_err(|err| RatingError::Json(err.to_string()))?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for RatingTable {
+    /// formats the ratings as a ladder with one player per line
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (rank, rating) in self.ratings.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>3}. {:<20} {:>6.0} ± {:>3.0} ({} games, {:.1}%)",
+                rank + 1,
+                rating.player,
+                rating.rating,
+                rating.uncertainty,
+                rating.games,
+                100.0 * rating.score
+            )?;
+        }
+        Ok(())
+    }
+}
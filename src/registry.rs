@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+#[cfg(feature = "tensorflow")]
+use std::sync::OnceLock;
+
+use super::config::ModelSignature;
+#[cfg(feature = "onnx")]
+use super::model::OnnxModel;
+#[cfg(feature = "tensorflow")]
+use super::model::TensorflowModel;
+
+/// loads every model only once and shares it between agents and their MCTS threads
+///
+/// models are identified by their path and signature. The registry only keeps weak
+/// references, so a model is unloaded as soon as the last agent using it is dropped
+/// and loaded again when it is requested the next time. Agents created by
+/// `MagisterLudi::with_config` use `ModelRegistry::global`, so two agents with the
+/// same model share it, while agents with different checkpoints can play each other
+/// in one process.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use rustai_abalone::config::ModelSignature;
+/// use rustai_abalone::evaluator::HeuristicEvaluator;
+/// use rustai_abalone::registry::ModelRegistry;
+/// let registry = ModelRegistry::new();
+/// let signature = ModelSignature::default();
+/// let first = registry.get_or_load("checkpoint_1", &signature, HeuristicEvaluator::default);
+/// let second = registry.get_or_load("checkpoint_1", &signature, || panic!("already loaded"));
+/// assert!(Arc::ptr_eq(&first, &second));
+/// ```
+pub struct ModelRegistry<M> {
+    models: Mutex<HashMap<(String, ModelSignature), Weak<M>>>,
+}
+
+impl<M> Default for ModelRegistry<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> ModelRegistry<M> {
+    /// creates an empty registry
+    pub fn new() -> Self {
+        Self {
+            models: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// returns the model for path and signature and loads it if it is not in use
+    ///
+    /// the registry stays locked while the model is loaded, so concurrent requests
+    /// for the same model wait instead of loading it twice. If loading panics the
+    /// registry stays usable and the model is loaded again on the next request
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path` - path the model is loaded from
+    /// * `signature` - names of the model's input and outputs and its input encoding
+    /// * `load` - loads the model if it is not in use
+    ///
+    /// # Returns
+    ///
+    /// * `model` - the shared model
+    ///
+    /// # Panics
+    ///
+    /// will pass on the panic of `load`
+    pub fn get_or_load<F: FnOnce() -> M>(&self, model_path: &str, signature: &ModelSignature, load: F) -> Arc<M> {
+        let mut models = self.lock();
+        let key = (model_path.to_string(), signature.clone());
+        if let Some(model) = models.get(&key).and_then(Weak::upgrade) {
+            return model;
+        }
+        // drop the entries of unloaded models while the registry is locked anyway
+        models.retain(|_, model| model.strong_count() > 0);
+        let model = Arc::new(load());
+        models.insert(key, Arc::downgrade(&model));
+        model
+    }
+
+    /// returns the model for path and signature if it is in use
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path` - path the model was loaded from
+    /// * `signature` - names of the model's input and outputs and its input encoding
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::ModelSignature;
+    /// use rustai_abalone::evaluator::HeuristicEvaluator;
+    /// use rustai_abalone::registry::ModelRegistry;
+    /// let registry = ModelRegistry::<HeuristicEvaluator>::new();
+    /// assert!(registry.get("checkpoint_1", &ModelSignature::default()).is_none());
+    /// ```
+    pub fn get(&self, model_path: &str, signature: &ModelSignature) -> Option<Arc<M>> {
+        let models = self.lock();
+        models
+            .get(&(model_path.to_string(), signature.clone()))
+            .and_then(Weak::upgrade)
+    }
+
+    /// returns the number of models that are in use
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::config::ModelSignature;
+    /// use rustai_abalone::evaluator::HeuristicEvaluator;
+    /// use rustai_abalone::registry::ModelRegistry;
+    /// let registry = ModelRegistry::new();
+    /// let model = registry.get_or_load("checkpoint_1", &ModelSignature::default(), HeuristicEvaluator::default);
+    /// assert_eq!(registry.len(), 1);
+    /// drop(model);
+    /// assert!(registry.is_empty());
+    /// ```
+    pub fn len(&self) -> usize {
+        let models = self.lock();
+        models.values().filter(|model| model.strong_count() > 0).count()
+    }
+
+    /// returns true if no model is in use
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // locks the models, a panic while loading a model leaves them consistent, so a poisoned lock is recovered
+    fn lock(&self) -> MutexGuard<'_, HashMap<(String, ModelSignature), Weak<M>>> {
+        self.models.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(feature = "tensorflow")]
+impl ModelRegistry<TensorflowModel> {
+    /// returns the registry shared by all agents of the process
+    pub fn global() -> &'static Self {
+        static GLOBAL: OnceLock<ModelRegistry<TensorflowModel>> = OnceLock::new();
+        GLOBAL.get_or_init(Self::new)
+    }
+
+    /// returns the tensorflow model in the given path and loads it if it is not in use
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path` - path to the folder containing `saved_model.pb`, see `util::check_model_present`
+    /// * `signature` - names of the model's input and outputs and its input encoding
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::sync::Arc;
+    /// use rustai_abalone::config::AgentConfig;
+    /// use rustai_abalone::player::MagisterLudi;
+    /// use rustai_abalone::registry::ModelRegistry;
+    /// let config = AgentConfig::default();
+    /// let model = ModelRegistry::global().load("checkpoints/42", &config.signature);
+    /// // both agents use the same session
+    /// let first = MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, config.clone(), model.clone());
+    /// let second = MagisterLudi::with_evaluator(rustai_abalone::game::BELGIAN_DAISY, config, model);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the model cannot be loaded or does not contain the signature names
+    pub fn load(&self, model_path: &str, signature: &ModelSignature) -> Arc<TensorflowModel> {
+        self.get_or_load(model_path, signature, || TensorflowModel::load(model_path, signature))
+    }
+}
+
+#[cfg(feature = "onnx")]
+impl ModelRegistry<OnnxModel> {
+    /// returns the ONNX model in the given file and loads it if it is not in use
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path` - path to the `.onnx` file
+    /// * `signature` - only its input encoding is used, ONNX models are run without names
+    ///
+    /// # Panics
+    ///
+    /// will panic if the file cannot be read or the model does not accept the encoded input
+    pub fn load(&self, model_path: &str, signature: &ModelSignature) -> Arc<OnnxModel> {
+        self.get_or_load(model_path, signature, || {
+            OnnxModel::load_with_encoder(model_path, signature.encoding.encoder())
+        })
+    }
+}
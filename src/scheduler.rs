@@ -0,0 +1,263 @@
+use crossbeam_channel::unbounded;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::config::AgentConfig;
+use super::evaluator::Evaluator;
+use super::game::{Board, BELGIAN_DAISY};
+use super::player::{DefaultEvaluator, MagisterLudi, SearchPool};
+use super::selfplay::{SelfPlayGame, SelfPlayRunner, DEFAULT_HISTORY_LEN, DEFAULT_MAX_MOVES};
+
+/// options of a `SelfPlayScheduler`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulerOptions {
+    /// number of games that are played at the same time, every worker thread plays one game
+    pub workers: usize,
+    /// number of threads of the search pool shared by all games, 0 to let every worker simulate
+    /// the leafs of its game on its own thread
+    pub search_threads: usize,
+    /// number of games to play, None to play until the scheduler is stopped
+    pub games: Option<usize>,
+    /// starting position of every game, black moves first
+    pub start_board: Board,
+    /// games are adjudicated as draw after this many moves
+    pub max_moves: usize,
+    /// number of previous boards recorded with every position, see `SelfPlayRunner::set_history_len`
+    pub history_len: usize,
+}
+
+impl Default for SchedulerOptions {
+    fn default() -> Self {
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        Self {
+            workers: threads,
+            search_threads: threads,
+            games: None,
+            start_board: BELGIAN_DAISY,
+            max_moves: DEFAULT_MAX_MOVES,
+            history_len: DEFAULT_HISTORY_LEN,
+        }
+    }
+}
+
+/// progress of a `SelfPlayScheduler`
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct SelfPlayProgress {
+    /// number of finished games
+    pub games: usize,
+    /// number of recorded positions of the finished games
+    pub positions: usize,
+    /// number of games that white won
+    pub white_wins: usize,
+    /// number of games that black won
+    pub black_wins: usize,
+    /// number of drawn or adjudicated games
+    pub draws: usize,
+    /// time since the scheduler started
+    pub elapsed: Duration,
+}
+
+impl SelfPlayProgress {
+    /// returns the number of finished games per hour
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use rustai_abalone::scheduler::SelfPlayProgress;
+    /// let progress = SelfPlayProgress { games: 10, elapsed: Duration::from_secs(1800), ..Default::default() };
+    /// assert_eq!(progress.games_per_hour(), 20.0);
+    /// ```
+    pub fn games_per_hour(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.games as f64 * 3600.0 / self.elapsed.as_secs_f64()
+    }
+
+    /// returns the number of recorded positions per second
+    pub fn positions_per_second(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.positions as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// stops a running `SelfPlayScheduler`, it can be cloned and moved to other threads
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle {
+    stop: Arc<AtomicBool>,
+    drain: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    /// asks the scheduler to stop, the running games are given up before their next move
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// returns true if the scheduler was asked to stop
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// asks the scheduler to finish the running games without starting new ones
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::scheduler::StopHandle;
+    /// let handle = StopHandle::default();
+    /// handle.drain();
+    /// assert!(handle.is_draining());
+    /// assert!(!handle.is_stopped());
+    /// ```
+    pub fn drain(&self) {
+        self.drain.store(true, Ordering::Relaxed);
+    }
+
+    /// returns true if the scheduler was asked to finish the running games
+    pub fn is_draining(&self) -> bool {
+        self.drain.load(Ordering::Relaxed)
+    }
+}
+
+/// plays many self-play games at the same time on a pool of worker threads
+///
+/// every worker plays its games with its own `MagisterLudi`. The agents of all workers hand their
+/// leafs to a single `player::SearchPool`, so the search threads stay busy while a worker plays its
+/// move or starts its next game. Without search threads every worker simulates its leafs itself.
+/// All agents share the evaluator, wrapping a model in a `batch::BatchedModel` lets the positions
+/// of all search threads be predicted in batches.
+///
+/// the scheduler does not handle signals. To shut it down gracefully on Ctrl-C, a signal handler
+/// has to call `StopHandle::drain` on the handle of `stop_handle`, like `examples/self_play.rs` does.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use rustai_abalone::config::AgentConfig;
+/// use rustai_abalone::evaluator::HeuristicEvaluator;
+/// use rustai_abalone::player::MoveSelection;
+/// use rustai_abalone::scheduler::{SchedulerOptions, SelfPlayScheduler};
+/// let config = AgentConfig::builder()
+///     .mcts_num(10)
+///     .mcts_depth(3)
+///     .move_selection(MoveSelection::self_play())
+///     .build()
+///     .unwrap();
+/// let options = SchedulerOptions { workers: 2, games: Some(2), max_moves: 4, ..Default::default() };
+/// let scheduler = SelfPlayScheduler::new(config, Arc::new(HeuristicEvaluator::default()), options);
+/// let progress = scheduler.run(|game_id, game, progress| {
+///     println!("game {game_id} ended with {}, {:.1} games per hour", game.result, progress.games_per_hour());
+/// });
+/// assert_eq!(progress.games, 2);
+/// ```
+pub struct SelfPlayScheduler<M: Evaluator + ?Sized + 'static = DefaultEvaluator> {
+    config: AgentConfig,
+    evaluator: Arc<M>,
+    options: SchedulerOptions,
+    stop: StopHandle,
+}
+
+impl<M: Evaluator + ?Sized + 'static> SelfPlayScheduler<M> {
+    /// creates a scheduler, the workers start with `run`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - search parameters of the agents, `mcts_parallel` is ignored as the agents use the search pool
+    ///   or their worker thread. With a seed every worker and search thread derives its own seed from it.
+    /// * `evaluator` - evaluator shared by all agents
+    /// * `options` - number of workers, search threads and games
+    pub fn new(config: AgentConfig, evaluator: Arc<M>, options: SchedulerOptions) -> Self {
+        Self {
+            config,
+            evaluator,
+            options,
+            stop: StopHandle::default(),
+        }
+    }
+
+    /// returns a handle to stop the scheduler, e.g. from a Ctrl-C handler installed by the caller
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    /// plays games until the number of games is reached or the scheduler is stopped
+    ///
+    /// the finished games are handed to the callback on the calling thread in the order they
+    /// finished. After a stop the running games are given up, the finished ones are kept.
+    /// When draining, the running games are finished and handed to the callback before `run` returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `on_game` - called with the ID, the records and the progress after every finished game
+    ///
+    /// # Returns
+    ///
+    /// * `progress` - the final progress
+    ///
+    /// # Panics
+    ///
+    /// will panic if the configuration is invalid or a worker panicked
+    pub fn run<F: FnMut(usize, SelfPlayGame, &SelfPlayProgress)>(&self, mut on_game: F) -> SelfPlayProgress {
+        let start = Instant::now();
+        let next_game = AtomicUsize::new(0);
+        let (game_sender, game_receiver) = unbounded();
+        let mut progress = SelfPlayProgress::default();
+        let search_pool = (self.options.search_threads > 0)
+            .then(|| Arc::new(SearchPool::new(self.options.search_threads, self.config.seed)));
+        thread::scope(|scope| {
+            for worker in 0..self.options.workers {
+                let game_sender = game_sender.clone();
+                let next_game = &next_game;
+                let mut config = AgentConfig {
+                    mcts_parallel: 0,
+                    ..self.config.clone()
+                };
+                config.seed = config.seed.map(|seed| seed.wrapping_add(worker as u64));
+                let evaluator = self.evaluator.clone();
+                let search_pool = search_pool.clone();
+                scope.spawn(move || {
+                    let agent = match search_pool {
+                        Some(search_pool) => MagisterLudi::with_search_pool(self.options.start_board, config, evaluator, search_pool),
+                        None => MagisterLudi::with_evaluator(self.options.start_board, config, evaluator),
+                    };
+                    let mut runner = SelfPlayRunner::new(agent, self.options.start_board, self.options.max_moves);
+                    runner.set_history_len(self.options.history_len);
+                    while !self.stop.is_stopped() && !self.stop.is_draining() {
+                        let game_id = next_game.fetch_add(1, Ordering::Relaxed);
+                        if self.options.games.is_some_and(|games| game_id >= games) {
+                            break;
+                        }
+                        match runner.play_until(&self.stop.stop) {
+                            // the receiver lives until all workers finished
+                            Some(game) => game_sender.send((game_id, game)).unwrap(),
+                            None => break,
+                        }
+                    }
+                    runner.into_agent().stop_execution();
+                });
+            }
+            // the receiver ends as soon as every worker dropped its sender
+            drop(game_sender);
+            for (game_id, game) in game_receiver.iter() {
+                progress.games += 1;
+                progress.positions += game.records.len();
+                match game.result {
+                    1 => progress.white_wins += 1,
+                    -1 => progress.black_wins += 1,
+                    _ => progress.draws += 1,
+                }
+                progress.elapsed = start.elapsed();
+                on_game(game_id, game, &progress);
+            }
+        });
+        progress.elapsed = start.elapsed();
+        progress
+    }
+}
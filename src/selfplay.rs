@@ -0,0 +1,357 @@
+#[cfg(feature = "npz")]
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::evaluator::{Evaluator, Position};
+use super::game::{AbaloneGame, Board};
+#[cfg(feature = "npz")]
+use super::game::BOARD_SIZE;
+#[cfg(feature = "npz")]
+use super::model::NUM_MOVES;
+#[cfg(feature = "npz")]
+use super::npz::{NpzError, NpzReader, NpzWriter};
+use super::player::{AgentAction, DefaultEvaluator, MagisterLudi};
+
+/// games are adjudicated as draw after this many moves if no other limit is given
+pub const DEFAULT_MAX_MOVES: usize = 300;
+
+/// number of previous boards recorded with every position if no other number is given
+pub const DEFAULT_HISTORY_LEN: usize = 8;
+
+/// position of a self-play game with the targets for training
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfPlayRecord {
+    /// position from the point of view of the side to move, see `AbaloneGame::get_rotated_state`
+    pub board: Board,
+    /// previous boards from the point of view of the side to move, the most recent first.
+    /// Contains at most as many boards as set with `SelfPlayRunner::set_history_len`
+    pub history: Vec<Board>,
+    /// true if black was to move
+    pub black_tomove: bool,
+    /// number of moves made before the position, including moves that were not recorded
+    pub move_number: usize,
+    /// share of the simulations per move ID, see `player::SearchReport::visit_distribution`
+    pub policy: Vec<f32>,
+    /// result of the game for the side to move, 1 for a win, -1 for a loss and 0 for a draw
+    pub outcome: f32,
+}
+
+impl SelfPlayRecord {
+    /// returns the recorded position, e.g. to encode it for training
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::game::BELGIAN_DAISY;
+    /// use rustai_abalone::model::NUM_MOVES;
+    /// use rustai_abalone::selfplay::SelfPlayRecord;
+    /// let record = SelfPlayRecord {
+    ///     board: BELGIAN_DAISY,
+    ///     history: Vec::new(),
+    ///     black_tomove: false,
+    ///     move_number: 0,
+    ///     policy: vec![0.0; NUM_MOVES],
+    ///     outcome: 1.0,
+    /// };
+    /// assert_eq!(record.position().num_moves, 0);
+    /// ```
+    pub fn position(&self) -> Position {
+        Position {
+            board: self.board,
+            history: self.history.clone(),
+            black_tomove: self.black_tomove,
+            num_moves: self.move_number,
+        }
+    }
+}
+
+/// records of a finished self-play game
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfPlayGame {
+    /// every searched position in the order it was played, moves of an opening book are not recorded
+    pub records: Vec<SelfPlayRecord>,
+    /// result of the game, 1 if white won, -1 if black won and 0 for a draw or an adjudicated game
+    pub result: i8,
+    /// number of moves played
+    pub num_moves: usize,
+}
+
+/// plays `MagisterLudi` against itself and records the positions for training
+///
+/// a single agent makes the moves of both sides. For varied games its move selection
+/// should add noise and temperature, see `player::MoveSelection::self_play`.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use rustai_abalone::config::AgentConfig;
+/// use rustai_abalone::evaluator::HeuristicEvaluator;
+/// use rustai_abalone::game::BELGIAN_DAISY;
+/// use rustai_abalone::player::{MagisterLudi, MoveSelection};
+/// use rustai_abalone::selfplay::SelfPlayRunner;
+/// let config = AgentConfig::builder()
+///     .mcts_num(10)
+///     .mcts_parallel(2)
+///     .mcts_depth(3)
+///     .move_selection(MoveSelection::self_play())
+///     .build()
+///     .unwrap();
+/// let agent = MagisterLudi::with_evaluator(BELGIAN_DAISY, config, Arc::new(HeuristicEvaluator::default()));
+/// let mut runner = SelfPlayRunner::new(agent, BELGIAN_DAISY, 6);
+/// let game = runner.play_game();
+/// assert_eq!(game.records.len(), 6);
+/// runner.into_agent().stop_execution();
+/// ```
+pub struct SelfPlayRunner<M: Evaluator + ?Sized + 'static = DefaultEvaluator> {
+    agent: MagisterLudi<M>,
+    start_board: Board,
+    max_moves: usize,
+    history_len: usize,
+}
+
+impl<M: Evaluator + ?Sized + 'static> SelfPlayRunner<M> {
+    /// creates a runner for the agent
+    ///
+    /// # Arguments
+    ///
+    /// * `agent` - agent playing both sides
+    /// * `start_board` - starting position of every game, black moves first
+    /// * `max_moves` - games are adjudicated as draw after this many moves, see `DEFAULT_MAX_MOVES`
+    pub fn new(agent: MagisterLudi<M>, start_board: Board, max_moves: usize) -> Self {
+        Self {
+            agent,
+            start_board,
+            max_moves,
+            history_len: DEFAULT_HISTORY_LEN,
+        }
+    }
+
+    /// sets the number of previous boards that are recorded with every position
+    ///
+    /// it should be at least the `history_len` of the encoder the records are trained with,
+    /// see `training::TrainingSample::from_self_play`
+    ///
+    /// # Arguments
+    ///
+    /// * `history_len` - maximum number of previous boards, `DEFAULT_HISTORY_LEN` by default
+    pub fn set_history_len(&mut self, history_len: usize) {
+        self.history_len = history_len;
+    }
+
+    /// returns the agent, e.g. to change its configuration between games
+    pub fn agent(&mut self) -> &mut MagisterLudi<M> {
+        &mut self.agent
+    }
+
+    /// returns the agent and consumes the runner
+    pub fn into_agent(self) -> MagisterLudi<M> {
+        self.agent
+    }
+
+    /// plays a game from the starting position until it ends or reaches the move limit
+    ///
+    /// a resignation ends the game as a loss for the resigning side, a draw offer is
+    /// considered by the agent for the side that received it
+    ///
+    /// # Panics
+    ///
+    /// will panic if one or more of the MCTS daemon threads of the agent are not active anymore
+    pub fn play_game(&mut self) -> SelfPlayGame {
+        self.play_until(&AtomicBool::new(false)).unwrap()
+    }
+
+    /// plays a game like `play_game`, but gives up the game as soon as the stop flag is set
+    ///
+    /// the flag is checked before every move, so a running search is finished first
+    ///
+    /// # Arguments
+    ///
+    /// * `stop` - flag to interrupt the game, e.g. set by a signal handler
+    ///
+    /// # Returns
+    ///
+    /// * `game` - the records of the game or None if it was interrupted
+    ///
+    /// # Panics
+    ///
+    /// will panic if one or more of the MCTS daemon threads of the agent are not active anymore
+    pub fn play_until(&mut self, stop: &AtomicBool) -> Option<SelfPlayGame> {
+        self.agent.start_new_game(self.start_board);
+        let mut referee = AbaloneGame::new(self.start_board);
+        // positions with their visit distributions, the outcomes are known after the game
+        let mut positions: Vec<(Position, Vec<f32>)> = Vec::new();
+        while !referee.get_game_ended() && referee.get_num_moves() < self.max_moves {
+            if stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            let black_tomove = referee.get_black_tomove();
+            let states = referee.get_state_history();
+            let previous: Vec<Board> = states[..states.len() - 1].iter().rev().take(self.history_len).copied().collect();
+            let position = Position::from_game(&referee, &previous, self.history_len);
+            let action = self.agent.own_move(true);
+            // moves from an opening book are played without search and are not recorded
+            if let Some(report) = self.agent.last_search_report() {
+                positions.push((position, report.visit_distribution()));
+            }
+            match action {
+                AgentAction::Move(next_state) => referee.update_state(next_state),
+                AgentAction::MoveOfferingDraw(next_state) => {
+                    referee.update_state(next_state);
+                    if self.agent.consider_draw_offer() {
+                        referee.end_with_result(0);
+                    }
+                }
+                AgentAction::Resign => referee.end_with_result(if black_tomove { 1 } else { -1 }),
+            }
+        }
+        let result = if referee.get_game_ended() { referee.get_game_result() } else { 0 };
+        let records = positions
+            .into_iter()
+            .map(|(position, policy)| {
+                let outcome = if position.black_tomove { -result } else { result };
+                SelfPlayRecord {
+                    board: position.board,
+                    history: position.history,
+                    black_tomove: position.black_tomove,
+                    move_number: position.num_moves,
+                    policy,
+                    outcome: outcome.into(),
+                }
+            })
+            .collect();
+        Some(SelfPlayGame {
+            records,
+            result,
+            num_moves: referee.get_num_moves(),
+        })
+    }
+}
+
+/// writes self-play records to an NPZ shard that can be loaded with `numpy.load`
+///
+/// only available with the `npz` feature. The shard contains these arrays:
+///
+/// * `boards` - `int8` of shape `[records, 11, 11]` with the field codes of the boards
+/// * `histories` - `int8` of shape `[records, history, 11, 11]` with the previous boards,
+///   `history` is the longest history of the records and shorter ones are filled with zeros
+/// * `history_lens` - `uint8` of shape `[records]` with the number of previous boards
+/// * `black_tomove` - `uint8` of shape `[records]`, 1 if black was to move
+/// * `move_numbers` - `uint32` of shape `[records]` with the number of moves before the position
+/// * `policies` - `float32` of shape `[records, 1506]` with the visit distributions
+/// * `values` - `float32` of shape `[records]` with the outcomes
+///
+/// the arrays `histories`, `history_lens`, `black_tomove` and `move_numbers` were added later.
+/// Shards written before only contain `boards`, `policies` and `values`, they no longer match
+/// `read_shard` and have to be regenerated.
+///
+/// # Arguments
+///
+/// * `path` - path of the shard, an existing file is overwritten
+/// * `records` - records to write
+///
+/// # Panics
+///
+/// will panic if a record has more than 255 previous boards
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::game::BELGIAN_DAISY;
+/// use rustai_abalone::model::NUM_MOVES;
+/// use rustai_abalone::selfplay::{read_shard, write_shard, SelfPlayRecord};
+/// let record = SelfPlayRecord {
+///     board: BELGIAN_DAISY,
+///     history: vec![BELGIAN_DAISY],
+///     black_tomove: true,
+///     move_number: 1,
+///     policy: vec![0.0; NUM_MOVES],
+///     outcome: 1.0,
+/// };
+/// let path = std::env::temp_dir().join("rustai_abalone_doc_shard.npz");
+/// write_shard(&path, &[record.clone()]).unwrap();
+/// assert_eq!(read_shard(&path).unwrap(), vec![record]);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[cfg(feature = "npz")]
+pub fn write_shard<P: AsRef<Path>>(path: P, records: &[SelfPlayRecord]) -> Result<(), NpzError> {
+    let boards: Vec<i8> = records.iter().flat_map(|record| record.board.iter().flatten().copied()).collect();
+    let history = records.iter().map(|record| record.history.len()).max().unwrap_or(0);
+    let mut histories: Vec<i8> = Vec::with_capacity(records.len() * history * BOARD_SIZE * BOARD_SIZE);
+    for record in records {
+        histories.extend(record.history.iter().flatten().flatten());
+        histories.resize(histories.len() + (history - record.history.len()) * BOARD_SIZE * BOARD_SIZE, 0);
+    }
+    let history_lens: Vec<u8> = records
+        .iter()
+        .map(|record| u8::try_from(record.history.len()).expect("at most 255 previous boards can be written"))
+        .collect();
+    let black_tomove: Vec<u8> = records.iter().map(|record| u8::from(record.black_tomove)).collect();
+    let move_numbers: Vec<u32> = records.iter().map(|record| record.move_number as u32).collect();
+    let policies: Vec<f32> = records.iter().flat_map(|record| record.policy.iter().copied()).collect();
+    let values: Vec<f32> = records.iter().map(|record| record.outcome).collect();
+    let mut writer = NpzWriter::create(path)?;
+    writer.add_array("boards", &[records.len(), BOARD_SIZE, BOARD_SIZE], &boards)?;
+    writer.add_array("histories", &[records.len(), history, BOARD_SIZE, BOARD_SIZE], &histories)?;
+    writer.add_array("history_lens", &[records.len()], &history_lens)?;
+    writer.add_array("black_tomove", &[records.len()], &black_tomove)?;
+    writer.add_array("move_numbers", &[records.len()], &move_numbers)?;
+    writer.add_array("policies", &[records.len(), NUM_MOVES], &policies)?;
+    writer.add_array("values", &[records.len()], &values)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// reads the self-play records of an NPZ shard written by `write_shard`
+///
+/// only available with the `npz` feature. Shards without the arrays `histories`, `history_lens`,
+/// `black_tomove` and `move_numbers` were written in the earlier format and are rejected with `NpzError::Zip`
+///
+/// # Arguments
+///
+/// * `path` - path of the shard
+#[cfg(feature = "npz")]
+pub fn read_shard<P: AsRef<Path>>(path: P) -> Result<Vec<SelfPlayRecord>, NpzError> {
+    let mut reader = NpzReader::open(path)?;
+    let boards = reader.read_array::<i8>("boards")?;
+    let histories = reader.read_array::<i8>("histories")?;
+    let history_lens = reader.read_array::<u8>("history_lens")?;
+    let black_tomove = reader.read_array::<u8>("black_tomove")?;
+    let move_numbers = reader.read_array::<u32>("move_numbers")?;
+    let policies = reader.read_array::<f32>("policies")?;
+    let values = reader.read_array::<f32>("values")?;
+    let num_records = values.data.len();
+    let history = histories.shape.get(1).copied().unwrap_or(0);
+    if boards.shape != [num_records, BOARD_SIZE, BOARD_SIZE]
+        || histories.shape != [num_records, history, BOARD_SIZE, BOARD_SIZE]
+        || history_lens.shape != [num_records]
+        || black_tomove.shape != [num_records]
+        || move_numbers.shape != [num_records]
+        || policies.shape != [num_records, NUM_MOVES]
+        || history_lens.data.iter().any(|len| usize::from(*len) > history)
+    {
+        return Err(NpzError::Format("the arrays of the shard do not match".to_string()));
+    }
+    let to_board = |fields: &[i8]| {
+        let mut board = [[0; BOARD_SIZE]; BOARD_SIZE];
+        for (row, chunk) in board.iter_mut().zip(fields.chunks_exact(BOARD_SIZE)) {
+            row.copy_from_slice(chunk);
+        }
+        board
+    };
+    let records = (0..num_records)
+        .map(|idx| {
+            let fields = BOARD_SIZE * BOARD_SIZE;
+            let previous = &histories.data[idx * history * fields..][..usize::from(history_lens.data[idx]) * fields];
+            SelfPlayRecord {
+                board: to_board(&boards.data[idx * fields..][..fields]),
+                history: previous.chunks_exact(fields).map(to_board).collect(),
+                black_tomove: black_tomove.data[idx] != 0,
+                move_number: move_numbers.data[idx] as usize,
+                policy: policies.data[idx * NUM_MOVES..][..NUM_MOVES].to_vec(),
+                outcome: values.data[idx],
+            }
+        })
+        .collect();
+    Ok(records)
+}
@@ -0,0 +1,501 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::config::ModelSignature;
+#[cfg(feature = "download")]
+use super::util::{download_model_with, DownloadError, DownloadOptions};
+use super::util::{check_model_present, file_sha256};
+
+/// environment variable with a directory that replaces the per-user cache directory
+pub const CACHE_DIR_ENV: &str = "RUSTAI_ABALONE_CACHE_DIR";
+
+/// name of the manifest file in the folder of every installed model
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// name of the model the library is trained with
+pub const DEFAULT_MODEL_NAME: &str = "magister_zero";
+
+/// version of the model the library is trained with
+pub const DEFAULT_MODEL_VERSION: &str = "1";
+
+/// name of the file in the store root that holds the folder name of the selected model
+const SELECTED_FILE: &str = "selected";
+
+/// error of the model store
+#[derive(Debug)]
+pub enum StoreError {
+    /// a file of the store could not be read or written
+    Io(io::Error),
+    /// a manifest is not valid JSON or misses fields
+    Manifest(String),
+    /// model names and versions may only contain ASCII letters, digits, `.`, `_` and `-` and must not start with `.`
+    InvalidName(String),
+    /// no model with this name and version is installed
+    NotInstalled(String),
+    /// a file of an installed model differs from its manifest, both checksums in hex
+    Checksum { file: String, expected: String, actual: String },
+    /// the default model could not be downloaded
+    #[cfg(feature = "download")]
+    Download(DownloadError),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access model store: {err}"),
+            Self::Manifest(msg) => write!(f, "invalid model manifest: {msg}"),
+            Self::InvalidName(name) => write!(f, "invalid model name or version '{name}'"),
+            Self::NotInstalled(model) => write!(f, "model {model} is not installed"),
+            Self::Checksum { file, expected, actual } => {
+                write!(f, "checksum of model file {file} is {actual}, expected {expected}")
+            }
+            #[cfg(feature = "download")]
+            Self::Download(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            #[cfg(feature = "download")]
+            Self::Download(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StoreError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "download")]
+impl From<DownloadError> for StoreError {
+    fn from(err: DownloadError) -> Self {
+        Self::Download(err)
+    }
+}
+
+/// description of an installed model, stored as `manifest.json` next to the model files
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::store::ModelManifest;
+/// let manifest = ModelManifest::new("magister_zero", "2");
+/// assert_eq!(manifest.id(), "magister_zero-2");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelManifest {
+    /// name of the model
+    pub name: String,
+    /// version of the model, models with the same name are told apart by it
+    pub version: String,
+    /// names of the model's input and outputs and its input encoding
+    #[serde(default)]
+    pub signature: ModelSignature,
+    /// SHA-256 checksums in hex of all model files, keyed by their path relative to the model
+    /// folder with `/` as separator. They are calculated when the model is installed
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+}
+
+impl ModelManifest {
+    /// creates a manifest with the default signature and without checksums
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the model
+    /// * `version` - version of the model
+    pub fn new(name: &str, version: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            signature: ModelSignature::default(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// returns the name of the model's folder in the store, `<name>-<version>`
+    pub fn id(&self) -> String {
+        format!("{}-{}", self.name, self.version)
+    }
+
+    /// reads the manifest in the given model folder
+    ///
+    /// # Arguments
+    ///
+    /// * `model_dir` - folder containing the model files and `manifest.json`
+    pub fn read(model_dir: &Path) -> Result<Self, StoreError> {
+        let content = fs::read_to_string(model_dir.join(MANIFEST_FILE))?;
+        serde_json::from_str(&content).map_err(|err| StoreError::Manifest(err.to_string()))
+    }
+
+    // writes the manifest to the given model folder
+    fn write(&self, model_dir: &Path) -> Result<(), StoreError> {
+        let content = serde_json::to_string_pretty(self).map_err(|err| StoreError::Manifest(err.to_string()))?;
+        fs::write(model_dir.join(MANIFEST_FILE), content)?;
+        Ok(())
+    }
+}
+
+/// model in the store together with its location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledModel {
+    /// description of the model
+    pub manifest: ModelManifest,
+    /// folder containing the model files and the manifest
+    pub path: PathBuf,
+}
+
+impl InstalledModel {
+    /// returns the path the model can be loaded from, see `model::TensorflowModel::load`
+    pub fn model_path(&self) -> String {
+        self.path.to_str().unwrap().to_string()
+    }
+}
+
+/// returns the per-user cache directory of the library
+///
+/// `CACHE_DIR_ENV` takes precedence, otherwise `$XDG_CACHE_HOME/rustai_abalone`,
+/// `$HOME/.cache/rustai_abalone` or `%LOCALAPPDATA%\rustai_abalone` on Windows is used.
+/// If none of the variables is set, the folder is created in the temporary directory.
+///
+/// # Examples
+///
+/// ```rust
+/// let cache_dir = rustai_abalone::store::cache_dir();
+/// assert!(cache_dir.is_absolute());
+/// ```
+pub fn cache_dir() -> PathBuf {
+    let env = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if let Some(dir) = env(CACHE_DIR_ENV) {
+        return dir;
+    }
+    let base = if cfg!(windows) {
+        env("LOCALAPPDATA")
+    } else {
+        env("XDG_CACHE_HOME").or_else(|| env("HOME").map(|home| home.join(".cache")))
+    };
+    base.unwrap_or_else(std::env::temp_dir).join("rustai_abalone")
+}
+
+// checks that a model name or version is a valid part of a folder name
+fn check_name(name: &str) -> Result<(), StoreError> {
+    // a leading dot would hide the folder from list and forbids . and ..
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(StoreError::InvalidName(name.to_string()))
+    }
+}
+
+// collects the paths of all files in a folder relative to the root with / as separator
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            let relative: Vec<_> = path.strip_prefix(root).unwrap().iter().map(|c| c.to_string_lossy()).collect();
+            files.push(relative.join("/"));
+        }
+    }
+    Ok(())
+}
+
+// copies a folder with all its content
+fn copy_dir(source: &Path, target: &Path) -> io::Result<()> {
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target_path = target.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target_path)?;
+        } else {
+            fs::copy(entry.path(), target_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// installed models in a directory, by default the per-user cache directory
+///
+/// every model lives in `models/<name>-<version>` below the root of the store with its
+/// `manifest.json`. One of the models can be selected, agents without a configured
+/// `model_path` use it.
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::store::ModelStore;
+/// let store = ModelStore::new(std::env::temp_dir().join("rustai_abalone_doc_store"));
+/// for model in store.list().unwrap() {
+///     println!("{} in {}", model.manifest.id(), model.path.display());
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelStore {
+    root: PathBuf,
+}
+
+impl Default for ModelStore {
+    fn default() -> Self {
+        Self::new(cache_dir())
+    }
+}
+
+impl ModelStore {
+    /// opens the store in the given directory, it is created when the first model is installed
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - directory of the store
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// returns the directory of the store
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // folder of a model in the store
+    fn model_dir(&self, name: &str, version: &str) -> Result<PathBuf, StoreError> {
+        check_name(name)?;
+        check_name(version)?;
+        Ok(self.root.join("models").join(format!("{name}-{version}")))
+    }
+
+    /// returns all installed models ordered by name and version
+    ///
+    /// folders without a readable manifest are skipped
+    pub fn list(&self) -> Result<Vec<InstalledModel>, StoreError> {
+        let models_dir = self.root.join("models");
+        if !models_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut models = Vec::new();
+        for entry in fs::read_dir(models_dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            if let Ok(manifest) = ModelManifest::read(&path) {
+                models.push(InstalledModel { manifest, path });
+            }
+        }
+        models.sort_by(|a, b| (&a.manifest.name, &a.manifest.version).cmp(&(&b.manifest.name, &b.manifest.version)));
+        Ok(models)
+    }
+
+    /// returns the installed model with the given name and version
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the model
+    /// * `version` - version of the model
+    pub fn get(&self, name: &str, version: &str) -> Result<InstalledModel, StoreError> {
+        let path = self.model_dir(name, version)?;
+        if !path.join(MANIFEST_FILE).exists() {
+            return Err(StoreError::NotInstalled(format!("{name}-{version}")));
+        }
+        let manifest = ModelManifest::read(&path)?;
+        Ok(InstalledModel { manifest, path })
+    }
+
+    /// copies a model folder into the store and records the checksums of its files
+    ///
+    /// an installed model with the same name and version is replaced
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - folder containing the model files, see `util::check_model_present`
+    /// * `manifest` - description of the model, its checksums are replaced
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use rustai_abalone::store::{ModelManifest, ModelStore};
+    /// let store = ModelStore::default();
+    /// let model = store.install(std::path::Path::new("checkpoints/42"), ModelManifest::new("my_model", "42")).unwrap();
+    /// store.select(&model.manifest.name, &model.manifest.version).unwrap();
+    /// ```
+    pub fn install(&self, source: &Path, manifest: ModelManifest) -> Result<InstalledModel, StoreError> {
+        let target = self.model_dir(&manifest.name, &manifest.version)?;
+        // the model is copied next to its final folder and renamed once it is complete,
+        // hidden folders are skipped by list
+        let staging = self.root.join("models").join(format!(".{}.{}.tmp", manifest.id(), std::process::id()));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        copy_dir(source, &staging)?;
+        let result = self.finish_install(&staging, &target, manifest);
+        if staging.exists() {
+            let _ = fs::remove_dir_all(&staging);
+        }
+        result
+    }
+
+    // records the checksums of the staged model and moves it to its folder
+    fn finish_install(&self, staging: &Path, target: &Path, mut manifest: ModelManifest) -> Result<InstalledModel, StoreError> {
+        let mut files = Vec::new();
+        collect_files(staging, staging, &mut files)?;
+        manifest.files.clear();
+        for file in files.into_iter().filter(|file| file != MANIFEST_FILE) {
+            let checksum = file_sha256(&staging.join(&file))?;
+            manifest.files.insert(file, checksum);
+        }
+        manifest.write(staging)?;
+        if target.exists() {
+            fs::remove_dir_all(target)?;
+        }
+        fs::rename(staging, target)?;
+        Ok(InstalledModel {
+            manifest,
+            path: target.to_path_buf(),
+        })
+    }
+
+    /// downloads the model of the library and installs it as `magister_zero-1`
+    ///
+    /// only available with the `download` feature
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - source of the archive and the checks of the download
+    #[cfg(feature = "download")]
+    pub fn install_default(&self, options: &DownloadOptions) -> Result<InstalledModel, StoreError> {
+        let downloads = self.root.join("downloads");
+        let model_path = download_model_with(downloads.to_str().unwrap(), options)?;
+        let manifest = ModelManifest::new(DEFAULT_MODEL_NAME, DEFAULT_MODEL_VERSION);
+        let result = self.install(Path::new(&model_path), manifest);
+        // an error of the cleanup must not hide a failed install
+        let cleanup = fs::remove_dir_all(&downloads);
+        let model = result?;
+        cleanup?;
+        Ok(model)
+    }
+
+    /// checks that all files of an installed model match the checksums of its manifest
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - the installed model
+    pub fn verify(&self, model: &InstalledModel) -> Result<(), StoreError> {
+        for (file, expected) in model.manifest.files.iter() {
+            let actual = file_sha256(&model.path.join(file))?;
+            if &actual != expected {
+                return Err(StoreError::Checksum {
+                    file: file.clone(),
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// selects the model agents use if they are not given a model path
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the model
+    /// * `version` - version of the model
+    pub fn select(&self, name: &str, version: &str) -> Result<(), StoreError> {
+        let model = self.get(name, version)?;
+        fs::write(self.root.join(SELECTED_FILE), model.manifest.id())?;
+        Ok(())
+    }
+
+    /// returns the selected model, None if no model was selected or it was removed
+    pub fn selected(&self) -> Result<Option<InstalledModel>, StoreError> {
+        let id = match fs::read_to_string(self.root.join(SELECTED_FILE)) {
+            Ok(id) => id,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let path = self.root.join("models").join(id.trim());
+        if !path.join(MANIFEST_FILE).exists() {
+            return Ok(None);
+        }
+        let manifest = ModelManifest::read(&path)?;
+        Ok(Some(InstalledModel { manifest, path }))
+    }
+
+    /// removes an installed model, if it was selected the selection is cleared
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the model
+    /// * `version` - version of the model
+    pub fn remove(&self, name: &str, version: &str) -> Result<(), StoreError> {
+        let model = self.get(name, version)?;
+        if self.selected()?.is_some_and(|selected| selected.path == model.path) {
+            fs::remove_file(self.root.join(SELECTED_FILE))?;
+        }
+        fs::remove_dir_all(&model.path)?;
+        Ok(())
+    }
+
+    /// returns the model agents use if they are not given a model path
+    ///
+    /// this is the selected model or otherwise the installed model of the library, as long as
+    /// it is complete and its files match the checksums of its manifest.
+    /// With the `download` feature the model of the library is downloaded and
+    /// installed if it is missing or damaged, see `DownloadOptions::from_env`.
+    pub fn default_model(&self) -> Result<InstalledModel, StoreError> {
+        #[cfg(feature = "download")]
+        {
+            self.default_model_with(&DownloadOptions::from_env())
+        }
+        #[cfg(not(feature = "download"))]
+        {
+            self.installed_default()?
+                .ok_or_else(|| StoreError::NotInstalled(format!("{DEFAULT_MODEL_NAME}-{DEFAULT_MODEL_VERSION}")))
+        }
+    }
+
+    /// returns the model agents use if they are not given a model path, see `default_model`
+    ///
+    /// only available with the `download` feature
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - source of the archive and the checks of the download if the model of the library
+    ///   has to be installed
+    ///
+    /// # Returns
+    ///
+    /// * `model` - the selected or installed model, otherwise the error of the failed install
+    #[cfg(feature = "download")]
+    pub fn default_model_with(&self, options: &DownloadOptions) -> Result<InstalledModel, StoreError> {
+        match self.installed_default()? {
+            Some(model) => Ok(model),
+            None => self.install_default(options),
+        }
+    }
+
+    // returns the selected model or the installed model of the library if its files verify
+    fn installed_default(&self) -> Result<Option<InstalledModel>, StoreError> {
+        if let Some(model) = self.selected()? {
+            return Ok(Some(model));
+        }
+        match self.get(DEFAULT_MODEL_NAME, DEFAULT_MODEL_VERSION) {
+            // models copied into the store by hand must still be complete and unchanged
+            Ok(model) if check_model_present(&model.model_path()).is_some() && self.verify(&model).is_ok() => Ok(Some(model)),
+            _ => Ok(None),
+        }
+    }
+}
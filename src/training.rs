@@ -0,0 +1,495 @@
+#[cfg(feature = "npz")]
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::encoder::InputEncoder;
+use super::evaluator::Position;
+use super::game::{Board, BOARD_SIZE};
+use super::marble_moves::MOVES_IDX;
+use super::model::NUM_MOVES;
+#[cfg(feature = "npz")]
+use super::npz::{NpzError, NpzReader, NpzWriter};
+use super::selfplay::SelfPlayGame;
+
+/// index of the centre field in both board dimensions
+const CENTRE: i32 = 5;
+/// fields up to this distance from the centre are mapped by a symmetry, the
+/// remaining fields are off-board corners of the array and stay in place
+const SYMMETRY_RADIUS: i32 = 5;
+
+/// symmetry of the hexagonal board, a rotation by a multiple of 60 degrees
+/// that is optionally preceded by a reflection
+///
+/// the rules of Abalone do not change under these 12 symmetries, so a position and its policy
+/// can be transformed to obtain further training samples. The colours of the marbles are kept.
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::game::BELGIAN_DAISY;
+/// use rustai_abalone::training::Symmetry;
+/// // the belgian daisy looks the same after half a turn
+/// let half_turn = Symmetry { rotation: 3, reflect: false };
+/// assert_eq!(half_turn.transform_board(&BELGIAN_DAISY), BELGIAN_DAISY);
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Symmetry {
+    /// number of counterclockwise rotations by 60 degrees, between 0 and 5
+    pub rotation: u8,
+    /// if true, the board is reflected at its main diagonal before the rotation
+    pub reflect: bool,
+}
+
+impl Symmetry {
+    /// the symmetry that leaves every board unchanged
+    pub const IDENTITY: Self = Self {
+        rotation: 0,
+        reflect: false,
+    };
+
+    /// all 12 symmetries, ordered by their index
+    pub const ALL: [Self; 12] = {
+        let mut all = [Self::IDENTITY; 12];
+        let mut idx = 0;
+        while idx < 12 {
+            all[idx] = Self {
+                rotation: (idx % 6) as u8,
+                reflect: idx >= 6,
+            };
+            idx += 1;
+        }
+        all
+    };
+
+    /// returns the index of the symmetry in `Symmetry::ALL`
+    pub fn index(self) -> usize {
+        self.rotation as usize % 6 + if self.reflect { 6 } else { 0 }
+    }
+
+    /// returns the symmetry with the given index in `Symmetry::ALL`, if the index is below 12
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    /// returns the symmetry that undoes this one
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::game::GERMAN_DAISY;
+    /// use rustai_abalone::training::Symmetry;
+    /// let symmetry = Symmetry { rotation: 2, reflect: false };
+    /// let transformed = symmetry.transform_board(&GERMAN_DAISY);
+    /// assert_eq!(symmetry.inverse().transform_board(&transformed), GERMAN_DAISY);
+    /// ```
+    pub fn inverse(self) -> Self {
+        if self.reflect {
+            // a reflection followed by a rotation is a reflection as well
+            return self;
+        }
+        Self {
+            rotation: (6 - self.rotation % 6) % 6,
+            reflect: false,
+        }
+    }
+
+    // maps a row and column offset from the centre (or a move direction)
+    fn transform_offset(self, mut dx: i32, mut dy: i32) -> (i32, i32) {
+        if self.reflect {
+            (dx, dy) = (dy, dx);
+        }
+        for _ in 0..self.rotation % 6 {
+            (dx, dy) = (dx + dy, -dx);
+        }
+        (dx, dy)
+    }
+
+    // maps a field of the array, the corners outside the hexagon stay in place
+    fn transform_field(self, x: usize, y: usize) -> (usize, usize) {
+        let (dx, dy) = (x as i32 - CENTRE, y as i32 - CENTRE);
+        if dx.abs().max(dy.abs()).max((dx + dy).abs()) > SYMMETRY_RADIUS {
+            return (x, y);
+        }
+        let (dx, dy) = self.transform_offset(dx, dy);
+        ((dx + CENTRE) as usize, (dy + CENTRE) as usize)
+    }
+
+    /// returns the transformed board
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - board to transform
+    pub fn transform_board(self, board: &Board) -> Board {
+        let mut transformed = [[0; BOARD_SIZE]; BOARD_SIZE];
+        for (x, row) in board.iter().enumerate() {
+            for (y, field) in row.iter().enumerate() {
+                let (new_x, new_y) = self.transform_field(x, y);
+                transformed[new_x][new_y] = *field;
+            }
+        }
+        transformed
+    }
+
+    /// returns the transformed input of an `encoder::InputEncoder`
+    ///
+    /// the planes of every board field are moved to the transformed field, so this works
+    /// for every encoder whose planes describe the fields of the board
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - input of a single position with the shape `[11, 11, planes]`
+    /// * `planes` - number of planes per board field
+    ///
+    /// # Panics
+    ///
+    /// will panic if the length of the input does not match the number of planes
+    pub fn transform_planes(self, input: &[f32], planes: usize) -> Vec<f32> {
+        assert_eq!(input.len(), BOARD_SIZE * BOARD_SIZE * planes, "input does not match the number of planes");
+        let mut transformed = vec![0.0; input.len()];
+        for x in 0..BOARD_SIZE {
+            for y in 0..BOARD_SIZE {
+                let (new_x, new_y) = self.transform_field(x, y);
+                transformed[(new_x * BOARD_SIZE + new_y) * planes..][..planes]
+                    .copy_from_slice(&input[(x * BOARD_SIZE + y) * planes..][..planes]);
+            }
+        }
+        transformed
+    }
+
+    /// returns the ID of the transformed move, see `marble_moves::MOVES_IDX`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::marble_moves::MOVES_IDX;
+    /// use rustai_abalone::training::Symmetry;
+    /// // moving (1, 5) by (1, 0) becomes moving (5, 1) by (0, 1) after reflection
+    /// let reflection = Symmetry { rotation: 0, reflect: true };
+    /// assert_eq!(reflection.transform_move_id(MOVES_IDX["1510"]), MOVES_IDX["5101"]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// will panic if the ID is not below `model::NUM_MOVES`
+    pub fn transform_move_id(self, move_id: usize) -> usize {
+        move_permutations()[self.index()][move_id]
+    }
+
+    /// returns the transformed policy, the probability of every move is moved to the ID
+    /// of the transformed move
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - probability for every move ID
+    ///
+    /// # Panics
+    ///
+    /// will panic if the policy does not have `model::NUM_MOVES` entries
+    pub fn transform_policy(self, policy: &[f32]) -> Vec<f32> {
+        assert_eq!(policy.len(), NUM_MOVES, "policy does not cover all moves");
+        let permutation = &move_permutations()[self.index()];
+        let mut transformed = vec![0.0; NUM_MOVES];
+        for (move_id, probability) in policy.iter().enumerate() {
+            transformed[permutation[move_id]] = *probability;
+        }
+        transformed
+    }
+}
+
+// splits a key of MOVES_IDX into the marble coordinates and the move direction
+fn parse_move_key(key: &str) -> (Vec<(i32, i32)>, (i32, i32)) {
+    let mut values: Vec<i32> = Vec::new();
+    let mut negative = false;
+    for c in key.chars() {
+        if c == '-' {
+            negative = true;
+        } else {
+            let digit = c.to_digit(10).unwrap() as i32;
+            values.push(if negative { -digit } else { digit });
+            negative = false;
+        }
+    }
+    let direction = (values[values.len() - 2], values[values.len() - 1]);
+    let coords = values[..values.len() - 2].chunks_exact(2).map(|pair| (pair[0], pair[1])).collect();
+    (coords, direction)
+}
+
+// permutation of the move IDs for every symmetry, built once from the keys of MOVES_IDX
+fn move_permutations() -> &'static [Vec<usize>; 12] {
+    static PERMUTATIONS: OnceLock<[Vec<usize>; 12]> = OnceLock::new();
+    PERMUTATIONS.get_or_init(|| {
+        Symmetry::ALL.map(|symmetry| {
+            let mut permutation = vec![0; NUM_MOVES];
+            for (key, move_id) in MOVES_IDX.entries() {
+                let (coords, (dx, dy)) = parse_move_key(key);
+                let mut new_key = String::with_capacity(key.len());
+                for (x, y) in coords {
+                    let (new_x, new_y) = symmetry.transform_field(x as usize, y as usize);
+                    new_key.push_str(&format!("{new_x}{new_y}"));
+                }
+                let (new_dx, new_dy) = symmetry.transform_offset(dx, dy);
+                new_key.push_str(&format!("{new_dx}{new_dy}"));
+                permutation[*move_id] = MOVES_IDX[new_key.as_str()];
+            }
+            permutation
+        })
+    })
+}
+
+/// additional information about a training sample
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SampleMetadata {
+    /// ID of the game the position was taken from
+    pub game: u64,
+    /// number of moves made before the position
+    pub move_number: u32,
+    /// true if black was to move, the boards were rotated then
+    pub black_tomove: bool,
+    /// symmetry that was applied to the original position
+    pub symmetry: Symmetry,
+}
+
+/// position with the targets for training a policy-value network
+///
+/// Stored in NPZ files by `write_samples` with these arrays, `n` being the number of samples:
+///
+/// * `inputs` - `float32` of shape `[n, 11, 11, planes]`, the encoded position
+/// * `policies` - `float32` of shape `[n, 1506]`, the probability of every move ID of `marble_moves::MOVES_IDX`
+/// * `values` - `float32` of shape `[n]`, the result for the side to move between -1 and 1
+/// * `games` - `uint64` of shape `[n]`, the ID of the game
+/// * `move_numbers` - `uint32` of shape `[n]`, the number of moves made before the position
+/// * `black_tomove` - `uint8` of shape `[n]`, 1 if black was to move
+/// * `symmetries` - `uint8` of shape `[n]`, the index of the applied symmetry in `Symmetry::ALL`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingSample {
+    /// encoded position with the shape `[11, 11, planes]`, see `encoder::InputEncoder`
+    pub input: Vec<f32>,
+    /// probability for every move ID
+    pub policy: Vec<f32>,
+    /// result of the game for the side to move, 1 for a win, -1 for a loss and 0 for a draw
+    pub value: f32,
+    /// origin of the sample
+    pub metadata: SampleMetadata,
+}
+
+impl TrainingSample {
+    /// creates a sample by encoding a position
+    ///
+    /// # Arguments
+    ///
+    /// * `encoder` - encoder of the network that is trained
+    /// * `position` - position from the point of view of the side to move
+    /// * `policy` - probability for every move ID
+    /// * `value` - result of the game for the side to move
+    /// * `game` - ID of the game
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::encoder::OneHotEncoder;
+    /// use rustai_abalone::evaluator::Position;
+    /// use rustai_abalone::game::BELGIAN_DAISY;
+    /// use rustai_abalone::model::NUM_MOVES;
+    /// use rustai_abalone::training::TrainingSample;
+    /// let position = Position::from_board(BELGIAN_DAISY);
+    /// let sample = TrainingSample::new(&OneHotEncoder, &position, vec![0.0; NUM_MOVES], 1.0, 0);
+    /// assert_eq!(sample.input.len(), 11 * 11 * 4);
+    /// ```
+    pub fn new(encoder: &dyn InputEncoder, position: &Position, policy: Vec<f32>, value: f32, game: u64) -> Self {
+        let mut input = vec![0.0; BOARD_SIZE * BOARD_SIZE * encoder.planes()];
+        encoder.encode(position, &mut input);
+        Self {
+            input,
+            policy,
+            value,
+            metadata: SampleMetadata {
+                game,
+                move_number: position.num_moves as u32,
+                black_tomove: position.black_tomove,
+                symmetry: Symmetry::IDENTITY,
+            },
+        }
+    }
+
+    /// creates the samples of every position of a self-play game
+    ///
+    /// side to move, move number and previous boards are taken from the records, so positions
+    /// that were not recorded, e.g. moves of an opening book, do not shift them. Encoders
+    /// with a longer history than recorded get fewer previous boards.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoder` - encoder of the network that is trained
+    /// * `game` - self-play game
+    /// * `game_id` - ID of the game
+    pub fn from_self_play(encoder: &dyn InputEncoder, game: &SelfPlayGame, game_id: u64) -> Vec<Self> {
+        game.records
+            .iter()
+            .map(|record| Self::new(encoder, &record.position(), record.policy.clone(), record.outcome, game_id))
+            .collect()
+    }
+
+    /// returns the number of planes per board field of the input
+    pub fn planes(&self) -> usize {
+        self.input.len() / (BOARD_SIZE * BOARD_SIZE)
+    }
+
+    /// returns the sample transformed by a symmetry
+    ///
+    /// the symmetry is combined with the one that was already applied to the sample,
+    /// the value and the other metadata stay the same
+    ///
+    /// # Arguments
+    ///
+    /// * `symmetry` - symmetry to apply
+    pub fn transform(&self, symmetry: Symmetry) -> Self {
+        let applied = self.metadata.symmetry;
+        // a reflection after a rotation equals the reflection before the inverse rotation
+        let combined = Symmetry {
+            rotation: if symmetry.reflect {
+                (symmetry.rotation + 6 - applied.rotation % 6) % 6
+            } else {
+                (symmetry.rotation + applied.rotation) % 6
+            },
+            reflect: symmetry.reflect != applied.reflect,
+        };
+        Self {
+            input: symmetry.transform_planes(&self.input, self.planes()),
+            policy: symmetry.transform_policy(&self.policy),
+            value: self.value,
+            metadata: SampleMetadata {
+                symmetry: combined,
+                ..self.metadata
+            },
+        }
+    }
+
+    /// returns the sample under all 12 symmetries, starting with the sample itself
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustai_abalone::encoder::OneHotEncoder;
+    /// use rustai_abalone::evaluator::Position;
+    /// use rustai_abalone::game::BELGIAN_DAISY;
+    /// use rustai_abalone::model::NUM_MOVES;
+    /// use rustai_abalone::training::TrainingSample;
+    /// let sample = TrainingSample::new(&OneHotEncoder, &Position::from_board(BELGIAN_DAISY), vec![0.0; NUM_MOVES], 0.0, 0);
+    /// assert_eq!(sample.symmetries().len(), 12);
+    /// ```
+    pub fn symmetries(&self) -> Vec<Self> {
+        Symmetry::ALL.iter().map(|symmetry| self.transform(*symmetry)).collect()
+    }
+}
+
+/// writes training samples to an NPZ file, see `TrainingSample` for the arrays
+///
+/// only available with the `npz` feature
+///
+/// # Arguments
+///
+/// * `path` - path of the file, an existing file is overwritten
+/// * `samples` - samples with the same number of planes
+/// * `all_symmetries` - if true, every sample is written under all 12 symmetries
+///
+/// # Returns
+///
+/// * `written` - number of written samples
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::encoder::OneHotEncoder;
+/// use rustai_abalone::evaluator::Position;
+/// use rustai_abalone::game::BELGIAN_DAISY;
+/// use rustai_abalone::model::NUM_MOVES;
+/// use rustai_abalone::training::{read_samples, write_samples, TrainingSample};
+/// let sample = TrainingSample::new(&OneHotEncoder, &Position::from_board(BELGIAN_DAISY), vec![0.0; NUM_MOVES], 1.0, 0);
+/// let path = std::env::temp_dir().join("rustai_abalone_doc_samples.npz");
+/// assert_eq!(write_samples(&path, &[sample], true).unwrap(), 12);
+/// assert_eq!(read_samples(&path).unwrap().len(), 12);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+///
+/// # Panics
+///
+/// will panic if the samples differ in the number of planes
+#[cfg(feature = "npz")]
+pub fn write_samples<P: AsRef<Path>>(path: P, samples: &[TrainingSample], all_symmetries: bool) -> Result<usize, NpzError> {
+    let augmented: Vec<TrainingSample>;
+    let samples = if all_symmetries {
+        augmented = samples.iter().flat_map(TrainingSample::symmetries).collect();
+        augmented.as_slice()
+    } else {
+        samples
+    };
+    let planes = samples.first().map_or(0, TrainingSample::planes);
+    let num = samples.len();
+    let mut inputs = Vec::with_capacity(num * BOARD_SIZE * BOARD_SIZE * planes);
+    let mut policies = Vec::with_capacity(num * NUM_MOVES);
+    for sample in samples {
+        assert_eq!(sample.planes(), planes, "samples differ in the number of planes");
+        inputs.extend_from_slice(&sample.input);
+        policies.extend_from_slice(&sample.policy);
+    }
+    let values: Vec<f32> = samples.iter().map(|sample| sample.value).collect();
+    let games: Vec<u64> = samples.iter().map(|sample| sample.metadata.game).collect();
+    let move_numbers: Vec<u32> = samples.iter().map(|sample| sample.metadata.move_number).collect();
+    let black_tomove: Vec<u8> = samples.iter().map(|sample| u8::from(sample.metadata.black_tomove)).collect();
+    let symmetries: Vec<u8> = samples.iter().map(|sample| sample.metadata.symmetry.index() as u8).collect();
+    let mut writer = NpzWriter::create(path)?;
+    writer.add_array("inputs", &[num, BOARD_SIZE, BOARD_SIZE, planes], &inputs)?;
+    writer.add_array("policies", &[num, NUM_MOVES], &policies)?;
+    writer.add_array("values", &[num], &values)?;
+    writer.add_array("games", &[num], &games)?;
+    writer.add_array("move_numbers", &[num], &move_numbers)?;
+    writer.add_array("black_tomove", &[num], &black_tomove)?;
+    writer.add_array("symmetries", &[num], &symmetries)?;
+    writer.finish()?;
+    Ok(num)
+}
+
+/// reads the training samples of an NPZ file written by `write_samples`
+///
+/// only available with the `npz` feature
+///
+/// # Arguments
+///
+/// * `path` - path of the file
+#[cfg(feature = "npz")]
+pub fn read_samples<P: AsRef<Path>>(path: P) -> Result<Vec<TrainingSample>, NpzError> {
+    let mut reader = NpzReader::open(path)?;
+    let inputs = reader.read_array::<f32>("inputs")?;
+    let policies = reader.read_array::<f32>("policies")?;
+    let values = reader.read_array::<f32>("values")?;
+    let games = reader.read_array::<u64>("games")?;
+    let move_numbers = reader.read_array::<u32>("move_numbers")?;
+    let black_tomove = reader.read_array::<u8>("black_tomove")?;
+    let symmetries = reader.read_array::<u8>("symmetries")?;
+    let num = values.data.len();
+    let matching = inputs.shape.len() == 4
+        && inputs.shape[..3] == [num, BOARD_SIZE, BOARD_SIZE]
+        && policies.shape == [num, NUM_MOVES]
+        && [games.data.len(), move_numbers.data.len(), black_tomove.data.len(), symmetries.data.len()] == [num; 4];
+    if !matching {
+        return Err(NpzError::Format("the arrays of the samples do not match".to_string()));
+    }
+    let input_size = BOARD_SIZE * BOARD_SIZE * inputs.shape[3];
+    (0..num)
+        .map(|idx| {
+            let symmetry = Symmetry::from_index(symmetries.data[idx] as usize)
+                .ok_or_else(|| NpzError::Format(format!("invalid symmetry {}", symmetries.data[idx])))?;
+            Ok(TrainingSample {
+                input: inputs.data[idx * input_size..][..input_size].to_vec(),
+                policy: policies.data[idx * NUM_MOVES..][..NUM_MOVES].to_vec(),
+                value: values.data[idx],
+                metadata: SampleMetadata {
+                    game: games.data[idx],
+                    move_number: move_numbers.data[idx],
+                    black_tomove: black_tomove.data[idx] != 0,
+                    symmetry,
+                },
+            })
+        })
+        .collect()
+}
@@ -1,72 +1,393 @@
-use reqwest::blocking::get;
-use std::fs::{self, File};
-use std::io::{self, Cursor};
-use std::path::Path;
+#[cfg(feature = "download")]
+use reqwest::blocking::Client;
+#[cfg(feature = "download")]
+use reqwest::header::RANGE;
+#[cfg(feature = "download")]
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+#[cfg(feature = "download")]
+use std::fmt;
+use std::fs::File;
+#[cfg(feature = "download")]
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufReader};
+#[cfg(feature = "download")]
+use std::io::Read;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "download")]
+use std::time::Duration;
+#[cfg(feature = "download")]
 use zip::ZipArchive;
 
-const GIT_MODEL_PATH: &str = "https://github.com/harloc-AI/rustai_abalone/raw/main/src/magister_zero.zip";
+/// URL of the zipped tensorflow model on github
+#[cfg(feature = "download")]
+pub const MODEL_URL: &str = "https://github.com/harloc-AI/rustai_abalone/raw/main/src/magister_zero.zip";
 
-fn download_and_extract_zip(url: &str, output_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
-    // download .zip file
-    let response = get(url)?;
-    let bytes = response.bytes()?;
+/// environment variable with a mirror URL that replaces `MODEL_URL`
+#[cfg(feature = "download")]
+pub const MODEL_URL_ENV: &str = "RUSTAI_ABALONE_MODEL_URL";
 
-    // open .zip file bytes
-    let cursor = Cursor::new(bytes);
-    let mut zip = ZipArchive::new(cursor)?;
+/// SHA-256 checksum of the zip archive at `MODEL_URL` in hex, None as long as no checksum is pinned.
+/// It has to be updated together with the archive
+#[cfg(feature = "download")]
+pub const MODEL_SHA256: Option<&str> = None;
+
+/// environment variable with the expected SHA-256 checksum of the zip archive in hex that replaces `MODEL_SHA256`
+#[cfg(feature = "download")]
+pub const MODEL_SHA256_ENV: &str = "RUSTAI_ABALONE_MODEL_SHA256";
+
+/// environment variable that forbids network access if it is set to anything but `0` or `false`
+#[cfg(feature = "download")]
+pub const OFFLINE_ENV: &str = "RUSTAI_ABALONE_OFFLINE";
+
+/// name of the folder the model is extracted to
+pub(crate) const MODEL_FOLDER: &str = "magister_zero_unwrap_save";
+
+/// files of the model as path components relative to the model folder
+const MODEL_FILES: [&[&str]; 5] = [
+    &["saved_model.pb"],
+    &["keras_metadata.pb"],
+    &["fingerprint.pb"],
+    &["variables", "variables.index"],
+    &["variables", "variables.data-00000-of-00001"],
+];
+
+/// default limit for the extracted size of the model archive, 1 GiB
+#[cfg(feature = "download")]
+pub const MAX_EXTRACTED_SIZE: u64 = 1 << 30;
+
+/// name of the archive while it is downloaded, a partial archive is resumed by the next download
+#[cfg(feature = "download")]
+const PARTIAL_ARCHIVE: &str = "magister_zero.zip.part";
+
+/// error of a model download
+#[cfg(feature = "download")]
+#[derive(Debug)]
+pub enum DownloadError {
+    /// network access is forbidden by `OFFLINE_ENV` or `DownloadOptions::offline`
+    Offline,
+    /// the request failed before the server answered
+    Network(reqwest::Error),
+    /// the server answered with an unexpected status code
+    Http(u16),
+    /// the archive could not be written or extracted to the file system
+    Io(io::Error),
+    /// the archive is not a valid zip file
+    Zip(zip::result::ZipError),
+    /// the checksum of the archive differs from the expected one, both in hex
+    Checksum { expected: String, actual: String },
+    /// the archive does not contain the model files, see `check_model_present`
+    ModelMissing,
+    /// an entry of the archive would be extracted outside the target directory or is a symbolic link
+    UnsafeEntry(String),
+    /// an entry of the archive is not one of the model files
+    UnexpectedEntry(String),
+    /// the extracted files exceed the size limit in bytes, the entry is the one that crossed it
+    TooLarge { entry: String, limit: u64 },
+}
+
+#[cfg(feature = "download")]
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Offline => write!(f, "network access is forbidden, set {OFFLINE_ENV}=0 to download the model"),
+            Self::Network(err) => write!(f, "could not download model: {err}"),
+            Self::Http(status) => write!(f, "could not download model: server answered with status {status}"),
+            Self::Io(err) => write!(f, "could not store model: {err}"),
+            Self::Zip(err) => write!(f, "could not extract model: {err}"),
+            Self::Checksum { expected, actual } => {
+                write!(f, "checksum of the model archive is {actual}, expected {expected}")
+            }
+            Self::ModelMissing => write!(f, "the archive does not contain the model files"),
+            Self::UnsafeEntry(name) => write!(f, "the archive entry '{name}' points outside the model folder"),
+            Self::UnexpectedEntry(name) => write!(f, "the archive entry '{name}' is not a model file"),
+            Self::TooLarge { entry, limit } => {
+                write!(f, "the archive entry '{entry}' exceeds the extraction limit of {limit} bytes")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "download")]
+impl std::error::Error for DownloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Network(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::Zip(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "download")]
+impl From<reqwest::Error> for DownloadError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Network(err)
+    }
+}
+
+#[cfg(feature = "download")]
+impl From<io::Error> for DownloadError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "download")]
+impl From<zip::result::ZipError> for DownloadError {
+    fn from(err: zip::result::ZipError) -> Self {
+        Self::Zip(err)
+    }
+}
+
+/// source of the model archive and the checks of a download
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::util::DownloadOptions;
+/// let options = DownloadOptions {
+///     url: "http://127.0.0.1:8000/magister_zero.zip".to_string(),
+///     ..DownloadOptions::from_env()
+/// };
+/// ```
+#[cfg(feature = "download")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadOptions {
+    /// URL of the zip archive
+    pub url: String,
+    /// expected SHA-256 checksum of the archive in hex, the download fails on a mismatch.
+    /// If None the archive is not verified, a warning is printed and no partial archive is resumed
+    pub sha256: Option<String>,
+    /// if true, the download fails without accessing the network
+    pub offline: bool,
+    /// maximum number of bytes all files of the archive may have after extraction
+    pub max_extracted_size: u64,
+}
+
+#[cfg(feature = "download")]
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            url: MODEL_URL.to_string(),
+            sha256: MODEL_SHA256.map(|checksum| checksum.to_string()),
+            offline: false,
+            max_extracted_size: MAX_EXTRACTED_SIZE,
+        }
+    }
+}
+
+#[cfg(feature = "download")]
+impl DownloadOptions {
+    /// returns the default options overridden by `MODEL_URL_ENV`, `MODEL_SHA256_ENV` and `OFFLINE_ENV`
+    pub fn from_env() -> Self {
+        let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let defaults = Self::default();
+        Self {
+            url: env(MODEL_URL_ENV).unwrap_or(defaults.url),
+            sha256: env(MODEL_SHA256_ENV).or(defaults.sha256),
+            offline: env(OFFLINE_ENV).is_some_and(|value| value != "0" && !value.eq_ignore_ascii_case("false")),
+            max_extracted_size: defaults.max_extracted_size,
+        }
+    }
+}
+
+// downloads the archive to the given file, a partial file of an earlier attempt is continued
+#[cfg(feature = "download")]
+fn fetch_archive(url: &str, archive_path: &Path) -> Result<(), DownloadError> {
+    // the model is large, so only the connection is limited in time
+    let client = Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .timeout(None)
+        .build()?;
+    let present = fs::metadata(archive_path).map_or(0, |meta| meta.len());
+    let mut request = client.get(url);
+    if present > 0 {
+        request = request.header(RANGE, format!("bytes={present}-"));
+    }
+    let mut response = request.send()?;
+    let mut file = match response.status() {
+        // the server continues where the last attempt stopped
+        StatusCode::PARTIAL_CONTENT => OpenOptions::new().append(true).open(archive_path)?,
+        // the server ignores the range or there is nothing to resume
+        StatusCode::OK => File::create(archive_path)?,
+        // the partial file is already complete
+        StatusCode::RANGE_NOT_SATISFIABLE if present > 0 => return Ok(()),
+        status => return Err(DownloadError::Http(status.as_u16())),
+    };
+    response.copy_to(&mut file)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+// calculates the SHA-256 checksum of a file in hex
+pub(crate) fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+// checks that the relative path of an archive entry is one of the model files,
+// optionally inside the model folder
+#[cfg(feature = "download")]
+fn is_model_file(path: &Path) -> bool {
+    let components: Vec<&str> = path.iter().filter_map(|component| component.to_str()).collect();
+    let relative = match components.split_first() {
+        Some((&first, rest)) if first == MODEL_FOLDER => rest,
+        _ => &components[..],
+    };
+    MODEL_FILES.contains(&relative)
+}
+
+// extracts the model files of the zip archive to the output directory, every entry must be
+// one of the model files and all of them together must not exceed max_size bytes
+#[cfg(feature = "download")]
+fn extract_archive(archive_path: &Path, output_dir: &Path, max_size: u64) -> Result<(), DownloadError> {
+    let mut zip = ZipArchive::new(BufReader::new(File::open(archive_path)?))?;
 
     // create target directory if it does not exist
     fs::create_dir_all(output_dir)?;
 
-    // extract from .zip
+    let mut extracted: u64 = 0;
     for i in 0..zip.len() {
         let mut file = zip.by_index(i)?;
-        let outpath = std::path::Path::new(output_dir).join(file.name());
-
+        let name = file.name().to_string();
+        // absolute paths and paths leaving the directory with .. are rejected
+        let relative = match file.enclosed_name() {
+            Some(path) if !file.is_symlink() => path,
+            _ => return Err(DownloadError::UnsafeEntry(name)),
+        };
+        // folders are created for the files they contain
         if file.is_dir() {
-            // create folder
-            fs::create_dir_all(&outpath)?;
-        } else {
-            // extract file
-            if let Some(parent) = outpath.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            let mut outfile = File::create(&outpath)?;
-            io::copy(&mut file, &mut outfile)?;
+            continue;
         }
-
+        if !is_model_file(&relative) {
+            return Err(DownloadError::UnexpectedEntry(name));
+        }
+        let remaining = max_size - extracted;
+        if file.size() > remaining {
+            return Err(DownloadError::TooLarge { entry: name, limit: max_size });
+        }
+        let outpath = output_dir.join(relative);
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut outfile = File::create(&outpath)?;
+        // the size in the archive may be forged, so the extraction stops one byte after the limit
+        let written = io::copy(&mut (&mut file).take(remaining.saturating_add(1)), &mut outfile)?;
+        if written > remaining {
+            return Err(DownloadError::TooLarge { entry: name, limit: max_size });
+        }
+        extracted += written;
     }
 
     Ok(())
 }
 
 /// downloads the tensorflow model
-/// 
-/// downloads the tensorflow model for the library from github and stores it in the given folder.
+///
+/// only available with the `download` feature.
+/// Downloads the tensorflow model for the library from github and stores it in the given folder.
 /// At the given path the new folder `magister_zero_unwrap_save` will be created. A String
-/// representing that path will be returned
-/// 
+/// representing that path will be returned. The URL, the expected checksum and whether
+/// network access is allowed are read from the environment, see `DownloadOptions::from_env`.
+///
 /// # Arguments
-/// 
+///
 /// * `output_dir` - path to the target directory
-/// 
+///
 /// # Returns
-/// 
+///
 /// * `model_path` - path to the extracted model, that can be used to instantiate `MagisterLudi`
-/// 
+///
 /// # Examples
-/// 
-/// ```rust
+///
+/// ```rust,no_run
 /// use rustai_abalone::util::download_model;
-/// let model_path = download_model(".");
+/// let model_path = download_model(".").expect("model download failed");
+/// ```
+#[cfg(feature = "download")]
+pub fn download_model(output_dir: &str) -> Result<String, DownloadError> {
+    download_model_with(output_dir, &DownloadOptions::from_env())
+}
+
+/// downloads the tensorflow model with the given options
+///
+/// the archive is downloaded to `magister_zero.zip.part` in the target directory,
+/// an interrupted download is resumed by the next call. After its checksum is verified
+/// against `sha256` the archive is extracted to a temporary folder, which is renamed to
+/// `magister_zero_unwrap_save` once the model is complete. So the model folder is
+/// either missing or complete, even if the download fails halfway. Only the five model
+/// files are extracted, entries pointing outside the folder, any other files or files
+/// exceeding `max_extracted_size` make the download fail. An archive with a wrong checksum
+/// is removed, also if it was resumed, so the next call downloads it from scratch. Without
+/// a checksum a partial archive could belong to another version, so it is not resumed.
+///
+/// # Arguments
+///
+/// * `output_dir` - path to the target directory
+/// * `options` - source of the archive and the checks of the download
+///
+/// # Returns
+///
+/// * `model_path` - path to the extracted model, that can be used to instantiate `MagisterLudi`
+///
+/// # Examples
+///
+/// ```rust
+/// use rustai_abalone::util::{download_model_with, DownloadError, DownloadOptions};
+/// let options = DownloadOptions { offline: true, ..DownloadOptions::default() };
+/// let result = download_model_with(".", &options);
+/// assert!(matches!(result, Err(DownloadError::Offline)));
 /// ```
-pub fn download_model(output_dir: &str) -> String {
-    let model_path = Path::new(output_dir).join("magister_zero_unwrap_save");
-    if let Err(e) = download_and_extract_zip(GIT_MODEL_PATH, output_dir) {
-        eprintln!("Error: {}", e);
+#[cfg(feature = "download")]
+pub fn download_model_with(output_dir: &str, options: &DownloadOptions) -> Result<String, DownloadError> {
+    if options.offline {
+        return Err(DownloadError::Offline);
+    }
+    let output_dir = Path::new(output_dir);
+    fs::create_dir_all(output_dir)?;
+    let archive_path = output_dir.join(PARTIAL_ARCHIVE);
+    match &options.sha256 {
+        Some(expected) => {
+            fetch_archive(&options.url, &archive_path)?;
+            // a resumed archive may continue a partial file of another version, so it is always verified
+            let actual = file_sha256(&archive_path)?;
+            if !actual.eq_ignore_ascii_case(expected) {
+                // the archive is corrupted, so the next attempt starts from scratch
+                fs::remove_file(&archive_path)?;
+                return Err(DownloadError::Checksum { expected: expected.clone(), actual });
+            }
+        }
+        None => {
+            eprintln!("warning: the model archive is not verified, set {MODEL_SHA256_ENV} to its SHA-256 checksum");
+            if archive_path.exists() {
+                fs::remove_file(&archive_path)?;
+            }
+            fetch_archive(&options.url, &archive_path)?;
+        }
     }
-    let model_path_str = model_path.to_str().unwrap().to_string();
-    model_path_str
+
+    let temp_dir = output_dir.join(format!(".{MODEL_FOLDER}.{}.tmp", std::process::id()));
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir)?;
+    }
+    let result = extract_archive(&archive_path, &temp_dir, options.max_extracted_size).and_then(|()| {
+        let extracted = check_model_present(temp_dir.to_str().unwrap()).ok_or(DownloadError::ModelMissing)?;
+        let model_path = output_dir.join(MODEL_FOLDER);
+        // an incomplete model of an earlier version is replaced
+        if model_path.exists() {
+            fs::remove_dir_all(&model_path)?;
+        }
+        fs::rename(extracted, &model_path)?;
+        Ok(model_path)
+    });
+    let _ = fs::remove_dir_all(&temp_dir);
+    // a rejected archive is not resumed by the next attempt
+    fs::remove_file(&archive_path)?;
+    let model_path = result?;
+    Ok(model_path.to_str().unwrap().to_string())
 }
 
 /// checks whether the tensorflow model is present
@@ -103,19 +424,12 @@ pub fn check_model_present(model_str: &str) -> Option<String> {
     if !model_path.exists() {
         return None;
     }
-    let mag_zero_path = model_path.join("magister_zero_unwrap_save");
+    let mag_zero_path = model_path.join(MODEL_FOLDER);
     if mag_zero_path.exists() {
         return check_model_present(mag_zero_path.to_str().unwrap());
     }
-    let to_check = [
-        "saved_model.pb",
-        "keras_metadata.pb",
-        "fingerprint.pb",
-        Box::leak(format!("variables{}variables.index", std::path::MAIN_SEPARATOR).into_boxed_str()),
-        Box::leak(format!("variables{}variables.data-00000-of-00001", std::path::MAIN_SEPARATOR).into_boxed_str()),
-    ];
-    for file in to_check {
-        if !model_path.join(file).exists(){
+    for file in MODEL_FILES {
+        if !model_path.join(file.iter().collect::<PathBuf>()).exists() {
             return None;
         }
     }